@@ -0,0 +1,362 @@
+//! UFO source backups before in-place formatting and backup restores
+//!
+//! Backups are full copies of the UFO source directory in a backup directory.
+//! The paths of the backups that were created in a run are recorded in a
+//! manifest file in the backup directory so that `ufofmt restore` can roll
+//! back the last run.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tempdir::TempDir;
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::utils;
+
+pub(crate) const MANIFEST_FILENAME: &str = ".ufofmt-backup.json";
+/// Backup directory in the working directory.  The directory is hidden so that
+/// UFO source discovery does not search it.
+pub(crate) const DEFAULT_BACKUP_DIR: &str = ".ufofmt-backups";
+/// Backup subdirectory for UFO sources outside of the working directory
+const EXTERNAL_SOURCES_DIR: &str = "_external";
+
+/// A UFO source backup
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Backup {
+    pub(crate) source: PathBuf,
+    pub(crate) backup: PathBuf,
+}
+
+/// Returns a UTC timestamp string for backup directory names, e.g. 20211018T143002Z
+pub(crate) fn get_timestamp_str(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let (days, day_secs) = (secs / 86_400, secs % 86_400);
+    // civil date from the number of days since 1970-01-01
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        (day_secs % 3600) / 60,
+        day_secs % 60
+    )
+}
+
+/// Returns `backup_dir`, or the default backup directory when it is not defined
+pub(crate) fn get_backup_dir(backup_dir: &Option<PathBuf>) -> PathBuf {
+    match backup_dir {
+        Some(dir) => dir.clone(),
+        None => PathBuf::from(DEFAULT_BACKUP_DIR),
+    }
+}
+
+/// Returns the backup path for a UFO source in the backup directory.  The
+/// source directory path relative to the working directory is mirrored in the
+/// backup directory, so UFO sources with the same name in different
+/// directories have distinct backup paths.
+pub(crate) fn get_backup_path(
+    ufopath: &Path,
+    backup_dir: &Option<PathBuf>,
+    timestamp: &str,
+) -> Result<PathBuf> {
+    let backup_path =
+        utils::get_ufo_outpath(ufopath, &Some(format!("-backup-{}", timestamp)), &None)?;
+    // get_ufo_outpath fails on paths without a directory name
+    let file_name = backup_path.file_name().unwrap_or_default();
+    let source_dir = get_source_dir(ufopath).map_err(|e| Error::Io(ufopath.into(), e))?;
+    Ok(get_backup_dir(backup_dir).join(source_dir).join(file_name))
+}
+
+/// Returns the directory of a UFO source relative to the working directory.
+/// Sources outside of the working directory are mapped to a directory that is
+/// named after the FNV-1a hash of their absolute directory path.
+fn get_source_dir(ufopath: &Path) -> std::io::Result<PathBuf> {
+    let abs_path = utils::get_absolute_path(ufopath)?;
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let abs_dir = abs_path.parent().unwrap_or(&abs_path);
+    match abs_dir.strip_prefix(&cwd) {
+        Ok(reldir) => Ok(reldir.to_path_buf()),
+        Err(_) => {
+            let hash = utils::fnv1a(abs_dir.to_string_lossy().as_bytes());
+            Ok(Path::new(EXTERNAL_SOURCES_DIR).join(format!("{:016x}", hash)))
+        }
+    }
+}
+
+/// Returns true for backup paths with a `-backup-<timestamp>` suffix, which
+/// are skipped when directories are searched for UFO sources
+pub(crate) fn is_backup_path(path: &Path) -> bool {
+    let stem = match path.file_stem() {
+        Some(stem) => stem.to_string_lossy(),
+        None => return false,
+    };
+    match stem.rsplit_once("-backup-") {
+        Some((_, timestamp)) => {
+            let ts = timestamp.as_bytes();
+            ts.len() == 16
+                && ts[..8].iter().all(u8::is_ascii_digit)
+                && ts[8] == b'T'
+                && ts[9..15].iter().all(u8::is_ascii_digit)
+                && ts[15] == b'Z'
+        }
+        None => false,
+    }
+}
+
+/// Copy a UFO source to its backup path. Fails if the backup path exists.
+pub(crate) fn backup_ufo(
+    ufopath: &Path,
+    backup_dir: &Option<PathBuf>,
+    timestamp: &str,
+) -> Result<Backup> {
+    if !ufopath.exists() {
+        return Err(Error::InvalidPath(ufopath.into()));
+    }
+    let backup_path = get_backup_path(ufopath, backup_dir, timestamp)?;
+    if let Some(dir) = backup_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| Error::Io(dir.into(), e))?;
+    }
+    utils::copy_path(ufopath, &backup_path).map_err(|e| Error::Io(backup_path.clone(), e))?;
+    Ok(Backup { source: ufopath.into(), backup: backup_path })
+}
+
+/// Returns the manifest path in the backup directory
+pub(crate) fn get_manifest_path(backup_dir: &Option<PathBuf>) -> PathBuf {
+    get_backup_dir(backup_dir).join(MANIFEST_FILENAME)
+}
+
+/// Record the backups of a run. Paths are stored as absolute paths so that
+/// restores do not depend on the working directory.
+pub(crate) fn write_manifest(
+    manifest_path: &Path,
+    timestamp: &str,
+    backups: &[Backup],
+) -> Result<()> {
+    let absolute = |p: &Path| p.canonicalize().map_err(|e| Error::Io(p.into(), e));
+    let mut entries = Vec::new();
+    for b in backups {
+        entries.push(json!({
+            "source": absolute(&b.source)?.to_string_lossy(),
+            "backup": absolute(&b.backup)?.to_string_lossy(),
+        }));
+    }
+    let manifest = json!({"timestamp": timestamp, "backups": entries});
+    let manifest_str = serde_json::to_string_pretty(&manifest).unwrap_or_default();
+    fs::write(manifest_path, manifest_str + "\n").map_err(|e| Error::Io(manifest_path.into(), e))
+}
+
+pub(crate) fn read_manifest(manifest_path: &Path) -> Result<Vec<Backup>> {
+    let manifest_str =
+        fs::read_to_string(manifest_path).map_err(|e| Error::Io(manifest_path.into(), e))?;
+    let invalid = |msg: &str| Error::InvalidBackupManifest(manifest_path.into(), msg.to_string());
+    let manifest: Value =
+        serde_json::from_str(&manifest_str).map_err(|e| invalid(&e.to_string()))?;
+    let entries = manifest["backups"].as_array().ok_or_else(|| invalid("missing backups list"))?;
+    entries
+        .iter()
+        .map(|entry| match (entry["source"].as_str(), entry["backup"].as_str()) {
+            (Some(source), Some(backup)) => {
+                Ok(Backup { source: source.into(), backup: backup.into() })
+            }
+            _ => Err(invalid("backup entries require source and backup paths")),
+        })
+        .collect()
+}
+
+/// Replace a UFO source with its backup. Returns the restored source path.
+/// The backup is kept.  The backup is copied to a temporary directory next to
+/// the source and swapped in with renames, so the source is not modified when
+/// the copy fails.
+pub(crate) fn restore_ufo(backup: &Backup) -> Result<PathBuf> {
+    let source = &backup.source;
+    if !backup.backup.exists() {
+        return Err(Error::InvalidPath(backup.backup.clone()));
+    }
+    let io_err = |e| Error::Io(source.clone(), e);
+    let parent = match source.parent() {
+        Some(par) if !par.as_os_str().is_empty() => par,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent).map_err(io_err)?;
+    // the temporary directory and the replaced source are removed on drop
+    let staging_dir = TempDir::new_in(parent, ".ufofmt-restore").map_err(io_err)?;
+    let staged_path = staging_dir.path().join("restored");
+    utils::copy_path(&backup.backup, &staged_path).map_err(io_err)?;
+    if source.exists() {
+        let replaced_path = staging_dir.path().join("replaced");
+        fs::rename(source, &replaced_path).map_err(io_err)?;
+        if let Err(e) = fs::rename(&staged_path, source) {
+            // put the original source back
+            let _ = fs::rename(&replaced_path, source);
+            return Err(io_err(e));
+        }
+    } else {
+        fs::rename(&staged_path, source).map_err(io_err)?;
+    }
+    Ok(source.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use fs_extra::dir::{copy, CopyOptions};
+
+    #[test]
+    fn test_get_timestamp_str() {
+        assert_eq!(get_timestamp_str(UNIX_EPOCH), "19700101T000000Z");
+        let time = UNIX_EPOCH + Duration::from_secs(1_634_567_402);
+        assert_eq!(get_timestamp_str(time), "20211018T143002Z");
+        // leap day
+        let time = UNIX_EPOCH + Duration::from_secs(951_825_600);
+        assert_eq!(get_timestamp_str(time), "20000229T120000Z");
+    }
+
+    #[test]
+    fn test_get_backup_path_default() {
+        let bp =
+            get_backup_path(Path::new("one/two/three.ufo"), &None, "20211018T143002Z").unwrap();
+        assert_eq!(bp, PathBuf::from(".ufofmt-backups/one/two/three-backup-20211018T143002Z.ufo"));
+    }
+
+    #[test]
+    fn test_get_backup_path_backup_dir() {
+        let bp = get_backup_path(
+            Path::new("one/two/three.ufo"),
+            &Some(PathBuf::from("backups")),
+            "20211018T143002Z",
+        )
+        .unwrap();
+        assert_eq!(bp, PathBuf::from("backups/one/two/three-backup-20211018T143002Z.ufo"));
+    }
+
+    #[test]
+    fn test_get_backup_path_same_name() {
+        let ts = "20211018T143002Z";
+        let bp_a = get_backup_path(Path::new("a/Font.ufo"), &None, ts).unwrap();
+        let bp_b = get_backup_path(Path::new("./b/Font.ufo"), &None, ts).unwrap();
+        assert_eq!(bp_a, PathBuf::from(".ufofmt-backups/a/Font-backup-20211018T143002Z.ufo"));
+        assert_eq!(bp_b, PathBuf::from(".ufofmt-backups/b/Font-backup-20211018T143002Z.ufo"));
+
+        // sources outside of the working directory are mapped to hashed directories
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let bp_c = get_backup_path(&tmp_dir.path().join("c/Font.ufo"), &None, ts).unwrap();
+        let bp_d = get_backup_path(&tmp_dir.path().join("d/Font.ufo"), &None, ts).unwrap();
+        assert!(bp_c.starts_with(".ufofmt-backups/_external"));
+        assert_eq!(bp_c.file_name(), bp_a.file_name());
+        assert_ne!(bp_c, bp_d);
+    }
+
+    #[test]
+    fn test_is_backup_path() {
+        assert!(is_backup_path(Path::new("backups/three-backup-20211018T143002Z.ufo")));
+        assert!(is_backup_path(Path::new("Font-Bold-backup-20211018T143002Z")));
+        assert!(!is_backup_path(Path::new("three.ufo")));
+        assert!(!is_backup_path(Path::new("Font-backup-old.ufo")));
+        assert!(!is_backup_path(Path::new("Font-backup-20211018T1430Z.ufo")));
+    }
+
+    #[test]
+    fn test_backup_and_restore_roundtrip() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let fontinfo_path = test_ufo_path.join("fontinfo.plist");
+        let original_fontinfo = fs::read_to_string(&fontinfo_path).unwrap();
+
+        let backup_dir = Some(tmp_dir.path().join("backups"));
+        let res_backup = backup_ufo(&test_ufo_path, &backup_dir, "20211018T143002Z");
+        assert!(res_backup.is_ok());
+        let backup = res_backup.unwrap();
+        assert_eq!(
+            backup.backup,
+            get_backup_path(&test_ufo_path, &backup_dir, "20211018T143002Z").unwrap()
+        );
+        assert!(backup.backup.starts_with(tmp_dir.path().join("backups/_external")));
+        assert_eq!(
+            fs::read_to_string(backup.backup.join("fontinfo.plist")).unwrap(),
+            original_fontinfo
+        );
+
+        // a second backup with the same timestamp must not overwrite the first
+        let res_backup_again = backup_ufo(&test_ufo_path, &backup_dir, "20211018T143002Z");
+        assert!(matches!(res_backup_again, Err(Error::Io(_, _))));
+
+        let manifest_path = get_manifest_path(&backup_dir);
+        assert!(write_manifest(&manifest_path, "20211018T143002Z", &[backup]).is_ok());
+
+        // modify the source, then restore from the manifest
+        fs::write(&fontinfo_path, "changed").unwrap();
+        fs::write(test_ufo_path.join("stray.txt"), "stray").unwrap();
+        let backups = read_manifest(&manifest_path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].source, test_ufo_path.canonicalize().unwrap());
+        let res_restore = restore_ufo(&backups[0]);
+        assert!(res_restore.is_ok());
+        assert_eq!(fs::read_to_string(&fontinfo_path).unwrap(), original_fontinfo);
+        assert!(!test_ufo_path.join("stray.txt").exists());
+        assert!(backups[0].backup.exists());
+    }
+
+    #[test]
+    fn test_backup_invalid_path() {
+        let res = backup_ufo(Path::new("totally/bogus/path/test.ufo"), &None, "20211018T143002Z");
+        assert!(matches!(res, Err(Error::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_read_manifest_invalid() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let manifest_path = tmp_dir.path().join(MANIFEST_FILENAME);
+        assert!(matches!(read_manifest(&manifest_path), Err(Error::Io(_, _))));
+        fs::write(&manifest_path, "{\"backups\": [{\"source\": \"a.ufo\"}]}").unwrap();
+        assert!(matches!(read_manifest(&manifest_path), Err(Error::InvalidBackupManifest(_, _))));
+        fs::write(&manifest_path, "not json").unwrap();
+        assert!(matches!(read_manifest(&manifest_path), Err(Error::InvalidBackupManifest(_, _))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_failed_copy_keeps_source() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let source_path = tmp_dir.path().join("Font.ufo");
+        let backup_path = tmp_dir.path().join("Font-backup.ufo");
+        fs::create_dir(&source_path).unwrap();
+        fs::write(source_path.join("metainfo.plist"), "source").unwrap();
+        fs::create_dir(&backup_path).unwrap();
+        // the dangling symbolic link fails the backup copy
+        std::os::unix::fs::symlink("missing.plist", backup_path.join("metainfo.plist")).unwrap();
+
+        let backup = Backup { source: source_path.clone(), backup: backup_path };
+        assert!(matches!(restore_ufo(&backup), Err(Error::Io(_, _))));
+        assert_eq!(fs::read_to_string(source_path.join("metainfo.plist")).unwrap(), "source");
+        // the temporary directory is removed
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_restore_missing_backup() {
+        let backup = Backup {
+            source: PathBuf::from("totally/bogus/path/test.ufo"),
+            backup: PathBuf::from("totally/bogus/path/test-backup.ufo"),
+        };
+        assert!(matches!(restore_ufo(&backup), Err(Error::InvalidPath(_))));
+    }
+}
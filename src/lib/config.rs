@@ -0,0 +1,160 @@
+//! ufofmt configuration files
+//!
+//! The configuration file is a TOML file with formatting settings by UFO
+//! source file type and UFO source path patterns, e.g.:
+//!
+//! ```toml
+//! [glif]
+//! indent = "\t"
+//!
+//! [plist]
+//! indent = "  "
+//!
+//! [designspace]
+//! indent = "    "
+//!
+//! [files]
+//! exclude = ["**/vendor/**"]
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::lib::discovery;
+use crate::lib::errors::{Error, Result};
+use crate::lib::formatters;
+
+/// Configuration file that is used when a path is not defined on the command line
+pub(crate) const CONFIG_FILENAME: &str = "ufofmt.toml";
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// glif file settings
+    #[serde(default)]
+    pub(crate) glif: FileTypeConfig,
+    /// plist file settings
+    #[serde(default)]
+    pub(crate) plist: FileTypeConfig,
+    /// designspace file settings
+    #[serde(default)]
+    pub(crate) designspace: FileTypeConfig,
+    /// UFO source path patterns
+    #[serde(default)]
+    pub(crate) files: FilesConfig,
+}
+
+/// Formatting settings for a UFO source file type
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FileTypeConfig {
+    pub(crate) indent: Option<String>,
+}
+
+/// UFO source path glob patterns that are combined with the command line patterns
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FilesConfig {
+    #[serde(default)]
+    pub(crate) include: Vec<String>,
+    #[serde(default)]
+    pub(crate) exclude: Vec<String>,
+}
+
+/// Read and validate a configuration file
+pub(crate) fn load_config(config_path: &Path) -> Result<Config> {
+    let config_str =
+        fs::read_to_string(config_path).map_err(|e| Error::Io(config_path.into(), e))?;
+    let invalid = |msg: String| Error::InvalidConfig(config_path.into(), msg);
+    let config: Config = toml::from_str(&config_str).map_err(|e| invalid(e.to_string()))?;
+    let indents = [&config.glif.indent, &config.plist.indent, &config.designspace.indent];
+    for indent_str in indents.iter().copied().flatten() {
+        formatters::validate_indent_str(indent_str).map_err(|e| invalid(e.to_string()))?;
+    }
+    for patterns in &[&config.files.include, &config.files.exclude] {
+        discovery::compile_patterns(patterns).map_err(|e| invalid(e.to_string()))?;
+    }
+    Ok(config)
+}
+
+/// Returns the configuration from `config_path`, or from the `ufofmt.toml`
+/// file in the working directory when it exists. Defaults are used without
+/// a configuration file.
+pub(crate) fn get_config(config_path: &Option<PathBuf>) -> Result<Config> {
+    match config_path {
+        Some(path) => load_config(path),
+        None if Path::new(CONFIG_FILENAME).is_file() => load_config(Path::new(CONFIG_FILENAME)),
+        None => Ok(Config::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let config_path = tmp_dir.path().join(CONFIG_FILENAME);
+        fs::write(&config_path, "[glif]\nindent = \"\\t\"\n\n[plist]\nindent = \"  \"\n").unwrap();
+        let config = load_config(&config_path).unwrap();
+        assert_eq!(config.glif.indent, Some("\t".to_string()));
+        assert_eq!(config.plist.indent, Some("  ".to_string()));
+
+        // file type sections are optional
+        fs::write(&config_path, "[glif]\nindent = \"    \"\n").unwrap();
+        let config = load_config(&config_path).unwrap();
+        assert_eq!(config.glif.indent, Some("    ".to_string()));
+        assert_eq!(config.plist, FileTypeConfig::default());
+        assert_eq!(config.designspace, FileTypeConfig::default());
+        fs::write(&config_path, "[designspace]\nindent = \"  \"\n").unwrap();
+        let config = load_config(&config_path).unwrap();
+        assert_eq!(config.designspace.indent, Some("  ".to_string()));
+        fs::write(&config_path, "").unwrap();
+        assert_eq!(load_config(&config_path).unwrap(), Config::default());
+
+        fs::write(
+            &config_path,
+            "[files]\ninclude = [\"masters/*.ufo\"]\nexclude = [\"**/archive/**\"]\n",
+        )
+        .unwrap();
+        let config = load_config(&config_path).unwrap();
+        assert_eq!(config.files.include, vec!["masters/*.ufo".to_string()]);
+        assert_eq!(config.files.exclude, vec!["**/archive/**".to_string()]);
+    }
+
+    #[test]
+    fn test_load_config_invalid() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let config_path = tmp_dir.path().join(CONFIG_FILENAME);
+        assert!(matches!(load_config(&config_path), Err(Error::Io(_, _))));
+        for config_str in &[
+            "[glif]\nindent = \"--\"\n",
+            "[plist]\nindent = \"\"\n",
+            "[glif]\nindent = 2\n",
+            "[glif]\nwidth = 2\n",
+            "[designspace]\nindent = \" \t\"\n",
+            "[files]\nexclude = [\"a**\"]\n",
+            "[files]\nexclude = \"**/archive/**\"\n",
+            "not toml",
+        ] {
+            fs::write(&config_path, config_str).unwrap();
+            assert!(matches!(load_config(&config_path), Err(Error::InvalidConfig(_, _))));
+        }
+    }
+
+    #[test]
+    fn test_get_config_path() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let config_path = tmp_dir.path().join("custom.toml");
+        fs::write(&config_path, "[plist]\nindent = \"\\t\\t\"\n").unwrap();
+        let config = get_config(&Some(config_path)).unwrap();
+        assert_eq!(config.plist.indent, Some("\t\t".to_string()));
+        assert!(matches!(
+            get_config(&Some(tmp_dir.path().join("missing.toml"))),
+            Err(Error::Io(_, _))
+        ));
+    }
+}
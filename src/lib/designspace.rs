@@ -0,0 +1,102 @@
+//! designspace file formatter
+//!
+//! norad does not read or write designspace files, so they are reindented
+//! through a quick-xml read/write roundtrip.  Only the whitespace between
+//! markup is replaced.  Attributes, comments, and text contents are written
+//! as they are defined in the source file.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+
+/// Returns true for paths with a .designspace extension
+pub(crate) fn is_designspace_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(OsStr::new("designspace")))
+}
+
+/// Reindent designspace file contents with `indent_str`, a validated string of
+/// tab or space chars.  Whitespace-only text is kept when it is the only
+/// contents of an element, e.g., in `<string> </string>`.
+pub(crate) fn format_designspace(
+    contents: &[u8],
+    indent_str: &str,
+) -> std::result::Result<Vec<u8>, String> {
+    let mut reader = Reader::from_reader(contents);
+    let indent_char = indent_str.bytes().next().unwrap_or(b'\t');
+    let mut writer = Writer::new_with_indent(Vec::new(), indent_char, indent_str.len());
+    let mut buf = Vec::new();
+    let mut prev_is_start = false;
+    let mut depth = 0usize;
+    let mut pending_whitespace = None;
+    loop {
+        let event = reader
+            .read_event(&mut buf)
+            .map_err(|e| format!("{} at position {}", e, reader.buffer_position()))?;
+        let is_whitespace =
+            matches!(&event, Event::Text(text) if text.iter().all(u8::is_ascii_whitespace));
+        if is_whitespace {
+            if prev_is_start {
+                pending_whitespace = Some(event.into_owned());
+            }
+            prev_is_start = false;
+            buf.clear();
+            continue;
+        }
+        if let Some(whitespace) = pending_whitespace.take() {
+            if matches!(event, Event::End(_)) {
+                writer.write_event(whitespace).map_err(|e| e.to_string())?;
+            }
+        }
+        match event {
+            Event::Eof if depth > 0 => return Err("unexpected end of file".to_string()),
+            Event::Eof => break,
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        prev_is_start = matches!(event, Event::Start(_));
+        writer.write_event(event).map_err(|e| e.to_string())?;
+        buf.clear();
+    }
+    let mut formatted = writer.into_inner();
+    if !formatted.is_empty() {
+        formatted.push(b'\n');
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_designspace_path() {
+        assert!(is_designspace_path(Path::new("sources/Font.designspace")));
+        assert!(is_designspace_path(Path::new("Font.DesignSpace")));
+        assert!(!is_designspace_path(Path::new("Font.ufo")));
+        assert!(!is_designspace_path(Path::new("designspace")));
+    }
+
+    #[test]
+    fn test_format_designspace() {
+        let source = "<?xml version='1.0' encoding='UTF-8'?>\n<designspace format=\"4.1\">\n<axes>\n      <axis tag=\"wght\" name=\"Weight\" minimum=\"100\" maximum=\"900\" default=\"400\"/>\n</axes>\n<!-- masters -->\n<sources><source filename=\"Font-Regular.ufo\" name=\"Regular\">\n<location><dimension name=\"Weight\" xvalue=\"400\"/></location></source></sources>\n<lib>\n<dict>\n<key>a</key>\n<string> </string>\n<key>b</key>\n<string>x &amp; y</string>\n</dict>\n</lib>\n</designspace>";
+        let expected = "<?xml version='1.0' encoding='UTF-8'?>\n<designspace format=\"4.1\">\n  <axes>\n    <axis tag=\"wght\" name=\"Weight\" minimum=\"100\" maximum=\"900\" default=\"400\"/>\n  </axes>\n  <!-- masters -->\n  <sources>\n    <source filename=\"Font-Regular.ufo\" name=\"Regular\">\n      <location>\n        <dimension name=\"Weight\" xvalue=\"400\"/>\n      </location>\n    </source>\n  </sources>\n  <lib>\n    <dict>\n      <key>a</key>\n      <string> </string>\n      <key>b</key>\n      <string>x &amp; y</string>\n    </dict>\n  </lib>\n</designspace>\n";
+        let formatted = format_designspace(source.as_bytes(), "  ").unwrap();
+        assert_eq!(String::from_utf8(formatted.clone()).unwrap(), expected);
+        // formatting is idempotent
+        assert_eq!(format_designspace(&formatted, "  ").unwrap(), formatted);
+
+        let formatted = format_designspace(expected.as_bytes(), "\t").unwrap();
+        assert!(String::from_utf8(formatted).unwrap().contains("\n\t<axes>\n\t\t<axis "));
+    }
+
+    #[test]
+    fn test_format_designspace_invalid() {
+        assert!(format_designspace(b"<designspace><axes></designspace>", "\t").is_err());
+        assert!(format_designspace(b"<designspace><axes/>", "\t").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! UFO source discovery
+//!
+//! Directories that are not UFO sources are searched for UFO sources.  The
+//! UFO source paths are filtered with include and exclude glob patterns that
+//! are matched against the paths relative to the working directory.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::lib::backup;
+use crate::lib::errors::{Error, Result};
+use crate::lib::utils;
+
+/// `*` and `?` do not match path separators, `**` matches any number of directories
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Include and exclude glob patterns for UFO source paths
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(PathFilter { include: compile_patterns(include)?, exclude: compile_patterns(exclude)? })
+    }
+
+    /// Returns true when `path` matches an include pattern, or there are no
+    /// include patterns, and `path` does not match an exclude pattern
+    pub(crate) fn is_match(&self, path: &Path) -> bool {
+        let match_path = get_match_path(path);
+        let matches = |patterns: &[Pattern]| {
+            patterns.iter().any(|p| p.matches_path_with(&match_path, MATCH_OPTIONS))
+        };
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+/// Validate glob patterns
+pub(crate) fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|p| Pattern::new(p).map_err(|e| Error::InvalidPattern(p.clone(), e.msg.to_string())))
+        .collect()
+}
+
+/// Returns the UFO source paths in `paths` and in the directories in `paths`
+/// that match `filter`.  Directories without UFO sources are returned as UFO
+/// source paths so that the read errors are reported.
+pub(crate) fn find_ufo_sources(paths: &[PathBuf], filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let mut ufopaths = Vec::new();
+    for path in paths {
+        if path.is_dir() && !is_ufo_dir(path) {
+            let found = find_ufo_dirs(path).map_err(|e| Error::Io(path.clone(), e))?;
+            if !found.is_empty() {
+                ufopaths.extend(found.into_iter().filter(|p| filter.is_match(p)));
+                continue;
+            }
+        }
+        if filter.is_match(path) {
+            ufopaths.push(path.clone());
+        }
+    }
+    Ok(ufopaths)
+}
+
+/// UFO sources are directories with a metainfo.plist file or a .ufo extension
+pub(crate) fn is_ufo_dir(path: &Path) -> bool {
+    path.join("metainfo.plist").is_file()
+        || path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ufo"))
+}
+
+/// Returns the sorted UFO source paths in the `dir` directory tree.  UFO
+/// sources, hidden directories, and symbolic links are not searched, and UFO
+/// source backups are skipped.
+fn find_ufo_dirs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut ufopaths = Vec::new();
+    for entry in entries {
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !entry.file_type()?.is_dir() || is_hidden {
+            continue;
+        }
+        let path = entry.path();
+        if backup::is_backup_path(&path) {
+            continue;
+        }
+        match is_ufo_dir(&path) {
+            true => ufopaths.push(path),
+            false => ufopaths.extend(find_ufo_dirs(&path)?),
+        }
+    }
+    Ok(ufopaths)
+}
+
+/// Returns the path relative to the working directory without `.` components.
+/// Paths outside of the working directory are matched as they are defined.
+fn get_match_path(path: &Path) -> PathBuf {
+    let path = utils::get_ufo_relpath(path).unwrap_or_else(|| path.to_path_buf());
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter::default();
+        assert!(filter.is_match(Path::new("masters/Font.ufo")));
+
+        let filter =
+            PathFilter::new(&patterns(&["masters/*.ufo"]), &patterns(&["**/archive/**"])).unwrap();
+        assert!(filter.is_match(Path::new("masters/Font.ufo")));
+        assert!(filter.is_match(Path::new("./masters/Font.ufo")));
+        assert!(!filter.is_match(Path::new("masters/sub/Font.ufo")));
+        assert!(!filter.is_match(Path::new("sources/Font.ufo")));
+        let filter = PathFilter::new(&[], &patterns(&["**/archive/**"])).unwrap();
+        assert!(!filter.is_match(Path::new("masters/archive/Font.ufo")));
+        assert!(!filter.is_match(Path::new("archive/old/Font.ufo")));
+        assert!(filter.is_match(Path::new("masters/Font.ufo")));
+    }
+
+    #[test]
+    fn test_compile_patterns_invalid() {
+        let res = PathFilter::new(&patterns(&["a**"]), &[]);
+        assert!(matches!(res, Err(Error::InvalidPattern(p, _)) if p == "a**"));
+    }
+
+    #[test]
+    fn test_find_ufo_sources() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let root = tmp_dir.path();
+        for dir in &[
+            "masters/B.ufo/glyphs",
+            "masters/A.ufo",
+            "masters/archive/Old.ufo",
+            "masters/backups/A-backup-20211018T143002Z.ufo",
+            "vendor/Font-Regular",
+            ".git/Hidden.ufo",
+            "empty",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("vendor/Font-Regular/metainfo.plist"), "").unwrap();
+        // UFO sources are not searched
+        fs::create_dir_all(root.join("masters/A.ufo/data/Nested.ufo")).unwrap();
+
+        let found = find_ufo_sources(&[root.to_path_buf()], &PathFilter::default()).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                root.join("masters/A.ufo"),
+                root.join("masters/B.ufo"),
+                root.join("masters/archive/Old.ufo"),
+                root.join("vendor/Font-Regular"),
+            ]
+        );
+
+        let filter = PathFilter::new(&[], &patterns(&["**/archive/**", "**/vendor/**"])).unwrap();
+        let paths = vec![root.to_path_buf(), root.join("missing.ufo"), root.join("empty")];
+        assert_eq!(
+            find_ufo_sources(&paths, &filter).unwrap(),
+            vec![
+                root.join("masters/A.ufo"),
+                root.join("masters/B.ufo"),
+                root.join("missing.ufo"),
+                root.join("empty")
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use std::{fmt, path::PathBuf};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 use colored::*;
 use lazy_static::lazy_static;
@@ -9,14 +10,65 @@ pub(crate) type Result<T> = std::result::Result<T, Error>;
 lazy_static! {
     pub static ref ERROR_INDICATOR: ColoredString = "[ERROR]".red().bold();
     pub static ref OK_INDICATOR: ColoredString = "[OK]".green().bold();
+    pub static ref DRY_RUN_INDICATOR: ColoredString = "[DRY RUN]".yellow().bold();
+    pub static ref WARNING_INDICATOR: ColoredString = "[WARNING]".yellow().bold();
 }
 
 // ufofmt custom error type
 #[derive(Debug)]
 pub(crate) enum Error {
+    DesignspaceRead(PathBuf, String),
+    FeaRead(PathBuf, std::io::Error),
+    FeaWrite(PathBuf, std::io::Error),
+    Git(String, String),
+    InvalidBackupManifest(PathBuf, String),
+    InvalidConfig(PathBuf, String),
+    InvalidData(PathBuf, String),
+    InvalidImage(PathBuf, String),
+    InvalidIndentation(String),
+    InvalidOutDirSource(PathBuf),
     InvalidPath(PathBuf),
+    InvalidPathList(PathBuf, String),
+    InvalidPattern(String, String),
+    InvalidRevision(String),
+    InvalidUfoPath(PathBuf),
+    Io(PathBuf, std::io::Error),
+    LossyContents(PathBuf, String),
     NoradRead(PathBuf, norad::Error),
     NoradWrite(PathBuf, norad::Error),
+    OutputPathCollision(PathBuf, PathBuf, PathBuf),
+    UnsupportedFileType(PathBuf),
+    VerificationFailed(PathBuf, String),
+}
+
+impl Error {
+    /// Returns the path of the file or UFO source that the error is about
+    pub(crate) fn path(&self) -> Option<&Path> {
+        match &self {
+            Error::DesignspaceRead(p, _)
+            | Error::FeaRead(p, _)
+            | Error::FeaWrite(p, _)
+            | Error::InvalidBackupManifest(p, _)
+            | Error::InvalidConfig(p, _)
+            | Error::InvalidData(p, _)
+            | Error::InvalidImage(p, _)
+            | Error::InvalidOutDirSource(p)
+            | Error::InvalidPath(p)
+            | Error::InvalidPathList(p, _)
+            | Error::InvalidUfoPath(p)
+            | Error::Io(p, _)
+            | Error::LossyContents(p, _)
+            | Error::NoradRead(p, _)
+            | Error::NoradWrite(p, _)
+            | Error::OutputPathCollision(p, _, _)
+            | Error::UnsupportedFileType(p)
+            | Error::VerificationFailed(p, _) => Some(p),
+            Error::Git(_, _)
+            | Error::InvalidIndentation(_)
+            | Error::InvalidPattern(_, _)
+            | Error::InvalidRevision(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -28,9 +80,94 @@ impl fmt::Display for Error {
             Error::NoradWrite(p, e) => {
                 write!(f, "norad write error: {}: {}", p.display(), e)
             }
+            Error::DesignspaceRead(p, msg) => {
+                write!(f, "designspace read error: {}: {}", p.display(), msg)
+            }
+            Error::FeaRead(p, e) => {
+                write!(f, "feature file read error: {}: {}", p.display(), e)
+            }
+            Error::FeaWrite(p, e) => {
+                write!(f, "feature file write error: {}: {}", p.display(), e)
+            }
+            Error::Git(cmd, msg) => {
+                write!(f, "git error: {}: {}", cmd, msg)
+            }
+            Error::InvalidBackupManifest(p, msg) => {
+                write!(f, "invalid backup manifest error: {}: {}", p.display(), msg)
+            }
+            Error::InvalidConfig(p, msg) => {
+                write!(f, "invalid configuration error: {}: {}", p.display(), msg)
+            }
+            Error::InvalidData(p, msg) => {
+                write!(f, "invalid data file error: {}: {}", p.display(), msg)
+            }
+            Error::InvalidImage(p, msg) => {
+                write!(f, "invalid image error: {}: {}", p.display(), msg)
+            }
+            Error::InvalidIndentation(s) => {
+                write!(
+                    f,
+                    "invalid indentation error: {:?} is not one or more tab chars or one or more space chars",
+                    s
+                )
+            }
+            Error::InvalidOutDirSource(p) => {
+                write!(
+                    f,
+                    "invalid output directory source error: {} is not located in the working directory",
+                    p.display()
+                )
+            }
             Error::InvalidPath(p) => {
                 write!(f, "invalid path error: {} was not found", p.display())
             }
+            Error::InvalidPathList(p, msg) => {
+                write!(f, "invalid path list error: {}: {}", p.display(), msg)
+            }
+            Error::InvalidPattern(pattern, msg) => {
+                write!(f, "invalid pattern error: {:?}: {}", pattern, msg)
+            }
+            Error::InvalidRevision(rev) => {
+                write!(f, "invalid git revision error: {:?} starts with '-'", rev)
+            }
+            Error::InvalidUfoPath(p) => {
+                write!(f, "invalid UFO path error: {} does not have a directory name", p.display())
+            }
+            Error::Io(p, e) => {
+                write!(f, "I/O error: {}: {}", p.display(), e)
+            }
+            Error::LossyContents(p, msg) => {
+                write!(
+                    f,
+                    "lossy contents error: {}: contents would be discarded by formatting: {}",
+                    p.display(),
+                    msg
+                )
+            }
+            Error::OutputPathCollision(p1, p2, outp) => {
+                write!(
+                    f,
+                    "output path collision error: {} and {} are both written to {}",
+                    p1.display(),
+                    p2.display(),
+                    outp.display()
+                )
+            }
+            Error::UnsupportedFileType(p) => {
+                write!(
+                    f,
+                    "unsupported file type error: {} is not a UFO glif, plist, feature, or designspace file",
+                    p.display()
+                )
+            }
+            Error::VerificationFailed(p, msg) => {
+                write!(
+                    f,
+                    "verification error: {}: the written UFO source differs from the formatted source: {}",
+                    p.display(),
+                    msg
+                )
+            }
         }
     }
 }
@@ -41,12 +178,174 @@ impl std::error::Error for Error {}
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ufofmterror_path() {
+        let ufe = Error::InvalidPath(PathBuf::from("testpath.ufo"));
+        assert_eq!(ufe.path(), Some(Path::new("testpath.ufo")));
+        let ufe = Error::OutputPathCollision(
+            PathBuf::from("a.ufo"),
+            PathBuf::from("b.ufo"),
+            PathBuf::from("out.ufo"),
+        );
+        assert_eq!(ufe.path(), Some(Path::new("a.ufo")));
+        assert_eq!(Error::InvalidIndentation("\t ".to_string()).path(), None);
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_path_list() {
+        let ufe = Error::InvalidPathList(PathBuf::from("list.txt"), "line 2: msg".to_string());
+        assert_eq!(ufe.to_string(), "invalid path list error: list.txt: line 2: msg");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_pattern() {
+        let ufe = Error::InvalidPattern("a**".to_string(), "msg".to_string());
+        assert_eq!(ufe.to_string(), "invalid pattern error: \"a**\": msg");
+    }
+
+    #[test]
+    fn test_ufofmterror_designspace_read() {
+        let ufe = Error::DesignspaceRead(PathBuf::from("Font.designspace"), "msg".to_string());
+        assert_eq!(ufe.to_string(), "designspace read error: Font.designspace: msg");
+    }
+
+    #[test]
+    fn test_ufofmterror_git() {
+        let ufe = Error::Git("git diff".to_string(), "bad revision".to_string());
+        assert_eq!(ufe.to_string(), "git error: git diff: bad revision");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_revision() {
+        let ufe = Error::InvalidRevision("--output=a".to_string());
+        assert_eq!(ufe.to_string(), "invalid git revision error: \"--output=a\" starts with '-'");
+        assert_eq!(ufe.path(), None);
+    }
+
     #[test]
     fn test_ufofmterror_invalid_path() {
         let ufe = Error::InvalidPath(PathBuf::from("testpath.ufo"));
         assert_eq!(ufe.to_string(), "invalid path error: testpath.ufo was not found");
     }
 
+    #[test]
+    fn test_ufofmterror_fea_read() {
+        let ioe = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let ufe = Error::FeaRead(PathBuf::from("shared.fea"), ioe);
+        assert_eq!(ufe.to_string(), "feature file read error: shared.fea: missing");
+    }
+
+    #[test]
+    fn test_ufofmterror_fea_write() {
+        let ioe = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let ufe = Error::FeaWrite(PathBuf::from("shared.fea"), ioe);
+        assert_eq!(ufe.to_string(), "feature file write error: shared.fea: denied");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_backup_manifest() {
+        let ufe = Error::InvalidBackupManifest(PathBuf::from("m.json"), "bad".to_string());
+        assert_eq!(ufe.to_string(), "invalid backup manifest error: m.json: bad");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_config() {
+        let ufe = Error::InvalidConfig(PathBuf::from("ufofmt.toml"), "bad value".to_string());
+        assert_eq!(ufe.to_string(), "invalid configuration error: ufofmt.toml: bad value");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_data() {
+        let ufe = Error::InvalidData(PathBuf::from("test.ufo/data/a.txt"), "missing".to_string());
+        assert_eq!(ufe.to_string(), "invalid data file error: test.ufo/data/a.txt: missing");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_image() {
+        let ufe = Error::InvalidImage(
+            PathBuf::from("test.ufo/images/a.png"),
+            "missing PNG signature".to_string(),
+        );
+        assert_eq!(
+            ufe.to_string(),
+            "invalid image error: test.ufo/images/a.png: missing PNG signature"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_indentation() {
+        let ufe = Error::InvalidIndentation("\t ".to_string());
+        assert_eq!(
+            ufe.to_string(),
+            "invalid indentation error: \"\\t \" is not one or more tab chars or one or more space chars"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_ufo_path() {
+        let ufe = Error::InvalidUfoPath(PathBuf::from(".."));
+        assert_eq!(ufe.to_string(), "invalid UFO path error: .. does not have a directory name");
+    }
+
+    #[test]
+    fn test_ufofmterror_invalid_out_dir_source() {
+        let ufe = Error::InvalidOutDirSource(PathBuf::from("/tmp/test.ufo"));
+        assert_eq!(
+            ufe.to_string(),
+            "invalid output directory source error: /tmp/test.ufo is not located in the working directory"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_io() {
+        let ioe = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let ufe = Error::Io(PathBuf::from("test.ufo"), ioe);
+        assert_eq!(ufe.to_string(), "I/O error: test.ufo: missing");
+    }
+
+    #[test]
+    fn test_ufofmterror_lossy_contents() {
+        let ufe = Error::LossyContents(
+            PathBuf::from("test.ufo"),
+            "test.ufo/lib.plist: plist: XML comments are not preserved".to_string(),
+        );
+        assert_eq!(
+            ufe.to_string(),
+            "lossy contents error: test.ufo: contents would be discarded by formatting: test.ufo/lib.plist: plist: XML comments are not preserved"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_output_path_collision() {
+        let ufe = Error::OutputPathCollision(
+            PathBuf::from("a/Font.ufo"),
+            PathBuf::from("b/Font.ufo"),
+            PathBuf::from("/tmp/out/Font.ufo"),
+        );
+        assert_eq!(
+            ufe.to_string(),
+            "output path collision error: a/Font.ufo and b/Font.ufo are both written to /tmp/out/Font.ufo"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_unsupported_file_type() {
+        let ufe = Error::UnsupportedFileType(PathBuf::from("images/A.png"));
+        assert_eq!(
+            ufe.to_string(),
+            "unsupported file type error: images/A.png is not a UFO glif, plist, feature, or designspace file"
+        );
+    }
+
+    #[test]
+    fn test_ufofmterror_verification_failed() {
+        let ufe = Error::VerificationFailed(PathBuf::from("test.ufo"), "lib differs".to_string());
+        assert_eq!(
+            ufe.to_string(),
+            "verification error: test.ufo: the written UFO source differs from the formatted source: lib differs"
+        );
+    }
+
     #[test]
     fn test_ufofmterror_read() {
         let ne = norad::Error::MissingLayer("test".to_owned());
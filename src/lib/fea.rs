@@ -0,0 +1,532 @@
+//! OpenType feature file (features.fea) formatter
+//!
+//! The formatter is intentionally conservative.  It normalizes indentation
+//! by block depth, spacing around brackets and punctuation, and places each
+//! statement on its own line.  Comments are preserved, user defined line
+//! breaks inside statements are kept as continuation lines, and the bodies
+//! of `anonymous` blocks are written verbatim.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tempdir::TempDir;
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::line_endings::{self, LineEnding};
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// Glyph names, keywords, numbers, tags and class names
+    Word,
+    /// Single char punctuation: `{ } [ ] < > ( ) ; , = '`
+    Punct(char),
+    /// `#` comment, without the line ending
+    Comment,
+    /// Double quoted string
+    Str,
+    /// `include(path)` statement, text holds the path
+    Include,
+    /// Verbatim `anonymous` block body
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    /// Number of line breaks in the whitespace that precedes the token
+    newlines_before: usize,
+}
+
+fn is_punct(c: char) -> bool {
+    matches!(c, '{' | '}' | '[' | ']' | '<' | '>' | '(' | ')' | ';' | ',' | '=' | '\'')
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    let mut newlines = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            newlines += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let (kind, text) = if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            (TokenKind::Comment, text.trim_end().to_string())
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            let text: String = chars[start..i].iter().collect();
+            (TokenKind::Str, text.replace('\r', ""))
+        } else if c == '{' && is_anonymous_block_start(&tokens) {
+            i += 1;
+            tokens.push(Token {
+                kind: TokenKind::Punct('{'),
+                text: "{".into(),
+                newlines_before: newlines,
+            });
+            let tag = tokens[tokens.len() - 2].text.clone();
+            let (body, end) = read_anonymous_body(&chars, i, &tag);
+            i = end;
+            newlines = 0;
+            (TokenKind::Raw, body)
+        } else if is_punct(c) {
+            i += 1;
+            (TokenKind::Punct(c), c.to_string())
+        } else {
+            while i < chars.len()
+                && !chars[i].is_whitespace()
+                && !is_punct(chars[i])
+                && chars[i] != '#'
+                && chars[i] != '"'
+            {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            if text == "include" {
+                if let Some((path, end)) = read_include_path(&chars, i) {
+                    i = end;
+                    tokens.push(Token {
+                        kind: TokenKind::Include,
+                        text: path,
+                        newlines_before: newlines,
+                    });
+                    newlines = 0;
+                    continue;
+                }
+            }
+            (TokenKind::Word, text)
+        };
+        tokens.push(Token { kind, text, newlines_before: newlines });
+        newlines = 0;
+    }
+    tokens
+}
+
+/// Returns true if the previous tokens are `anon TAG` or `anonymous TAG`
+fn is_anonymous_block_start(tokens: &[Token]) -> bool {
+    match tokens {
+        [.., keyword, tag] => {
+            keyword.kind == TokenKind::Word
+                && (keyword.text == "anon" || keyword.text == "anonymous")
+                && tag.kind == TokenKind::Word
+        }
+        _ => false,
+    }
+}
+
+/// Reads an anonymous block body up to the line that starts with `} TAG`.
+/// Returns the body text and the index of the closing brace.
+fn read_anonymous_body(chars: &[char], start: usize, tag: &str) -> (String, usize) {
+    let mut i = start;
+    // skip trailing whitespace on the line of the opening brace
+    while i < chars.len() && chars[i] != '\n' && chars[i].is_whitespace() {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '\n' {
+        i += 1;
+    }
+    let body_start = i;
+    let mut line_start = i;
+    while line_start < chars.len() {
+        let mut j = line_start;
+        while j < chars.len() && chars[j] != '\n' && chars[j].is_whitespace() {
+            j += 1;
+        }
+        if j < chars.len() && chars[j] == '}' {
+            let rest: String = chars[j + 1..].iter().take(tag.len() + 64).collect();
+            if rest.trim_start().starts_with(tag) {
+                let body: String = chars[body_start..line_start].iter().collect();
+                return (body.replace('\r', ""), j);
+            }
+        }
+        while line_start < chars.len() && chars[line_start] != '\n' {
+            line_start += 1;
+        }
+        line_start += 1;
+    }
+    // unterminated block: keep the remainder of the file verbatim
+    let body: String = chars[body_start.min(chars.len())..].iter().collect();
+    (body.replace('\r', ""), chars.len())
+}
+
+/// Reads the `(path)` that follows an `include` keyword. Returns the trimmed
+/// path and the index that follows the closing parenthesis.
+fn read_include_path(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut i = start;
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    if i >= chars.len() || chars[i] != '(' {
+        return None;
+    }
+    let path_start = i + 1;
+    let close = chars[path_start..].iter().position(|c| *c == ')')? + path_start;
+    let path: String = chars[path_start..close].iter().collect();
+    Some((path.trim().to_string(), close + 1))
+}
+
+fn needs_space(prev: &Token, cur: &Token) -> bool {
+    !matches!(prev.kind, TokenKind::Punct('[') | TokenKind::Punct('<') | TokenKind::Punct('('))
+        && !matches!(
+            cur.kind,
+            TokenKind::Punct(']')
+                | TokenKind::Punct('>')
+                | TokenKind::Punct(')')
+                | TokenKind::Punct(';')
+                | TokenKind::Punct(',')
+                | TokenKind::Punct('\'')
+        )
+}
+
+/// Format OpenType feature file source text. Indentation uses one
+/// `indent_str` per block level.
+pub(crate) fn format_fea(source: &str, indent_str: &str) -> String {
+    let tokens = tokenize(source);
+    let mut out = String::new();
+    let mut depth: usize = 0;
+    // true when the current output line has content
+    let mut line_open = false;
+    // true when the next token must start a new line
+    let mut must_break = false;
+    // true when the current statement has started
+    let mut in_statement = false;
+    let mut prev: Option<&Token> = None;
+
+    for token in &tokens {
+        let is_closing_bracket = matches!(
+            token.kind,
+            TokenKind::Punct(']') | TokenKind::Punct('>') | TokenKind::Punct(')')
+        );
+
+        // trailing comments stay on the line of the preceding code
+        if token.kind == TokenKind::Comment && token.newlines_before == 0 && line_open {
+            out.push(' ');
+            out.push_str(&token.text);
+            must_break = true;
+            prev = Some(token);
+            continue;
+        }
+
+        if token.kind == TokenKind::Raw {
+            if line_open {
+                out.push('\n');
+            }
+            out.push_str(&token.text);
+            if !token.text.is_empty() && !token.text.ends_with('\n') {
+                out.push('\n');
+            }
+            line_open = false;
+            must_break = false;
+            prev = Some(token);
+            continue;
+        }
+
+        if token.kind == TokenKind::Punct('}') {
+            depth = depth.saturating_sub(1);
+        }
+
+        let after_comment = matches!(prev.map(|p| &p.kind), Some(TokenKind::Comment));
+        let joins_line = match token.kind {
+            TokenKind::Punct('{') | TokenKind::Punct(';') => line_open && !after_comment,
+            TokenKind::Punct('}') => false,
+            _ => line_open && !must_break && token.newlines_before == 0,
+        };
+
+        if joins_line {
+            if let Some(p) = prev {
+                if token.kind == TokenKind::Punct('{') || needs_space(p, token) {
+                    out.push(' ');
+                }
+            }
+        } else {
+            if line_open {
+                out.push('\n');
+            }
+            let after_block_open = matches!(prev.map(|p| &p.kind), Some(TokenKind::Punct('{')));
+            if token.newlines_before > 1
+                && !out.is_empty()
+                && !after_block_open
+                && token.kind != TokenKind::Punct('}')
+            {
+                out.push('\n');
+            }
+            let continuation =
+                in_statement && !is_closing_bracket && token.kind != TokenKind::Punct('{');
+            let level = if continuation { depth + 1 } else { depth };
+            for _ in 0..level {
+                out.push_str(indent_str);
+            }
+        }
+        match token.kind {
+            TokenKind::Include => out.push_str(&format!("include({})", token.text)),
+            _ => out.push_str(&token.text),
+        }
+        line_open = true;
+
+        match token.kind {
+            TokenKind::Punct('{') => {
+                depth += 1;
+                must_break = true;
+                in_statement = false;
+            }
+            TokenKind::Comment => {
+                must_break = true;
+            }
+            TokenKind::Punct(';') | TokenKind::Include => {
+                must_break = true;
+                in_statement = false;
+            }
+            _ => {
+                must_break = false;
+                in_statement = true;
+            }
+        }
+        prev = Some(token);
+    }
+
+    if line_open {
+        out.push('\n');
+    }
+    out
+}
+
+/// Returns the `include()` paths defined in feature file source text
+pub(crate) fn include_paths(source: &str) -> Vec<String> {
+    tokenize(source).into_iter().filter(|t| t.kind == TokenKind::Include).map(|t| t.text).collect()
+}
+
+/// Format the feature files that are included from `source` recursively, and
+/// write them in place when `write` is true.  Relative include paths are
+/// resolved against `include_dir`, the directory that contains the UFO source.
+/// Returns the paths of the include files that formatting changes.
+pub(crate) fn format_fea_includes(
+    source: &str,
+    include_dir: &Path,
+    indent_str: &str,
+    line_ending: LineEnding,
+    write: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let mut pending: Vec<PathBuf> =
+        include_paths(source).iter().map(|p| include_dir.join(p)).collect();
+    let mut changed = Vec::new();
+
+    while let Some(path) = pending.pop() {
+        if !visited.insert(path.clone()) {
+            continue;
+        }
+        let original = fs::read_to_string(&path).map_err(|e| Error::FeaRead(path.clone(), e))?;
+        pending.extend(include_paths(&original).iter().map(|p| include_dir.join(p)));
+        let fmt_source = format_fea(&original, indent_str);
+        let ending = line_endings::resolve_line_ending(line_ending, original.as_bytes());
+        let fmt_source = line_endings::convert_text_line_endings(&fmt_source, ending);
+        if fmt_source == original {
+            continue;
+        }
+        if write {
+            write_atomic(&path, &fmt_source).map_err(|e| Error::FeaWrite(path.clone(), e))?;
+        }
+        changed.push(path);
+    }
+    changed.sort();
+    Ok(changed)
+}
+
+/// Include files can be shared across UFO sources that are formatted in
+/// parallel.  Each write goes through a temporary file in a unique directory
+/// next to the include file and a rename, so that concurrent writers do not
+/// share a temporary file and readers never observe a partially written file.
+fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(par) if !par.as_os_str().is_empty() => par,
+        _ => Path::new("."),
+    };
+    let tmp_dir = TempDir::new_in(dir, ".ufofmt-tmp")?;
+    let tmp_path = tmp_dir.path().join("include.fea");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_format_fea_blocks_and_spacing() {
+        let source = "languagesystem DFLT dflt;languagesystem latn dflt;\n\
+            @lc=[ a b  c ];\n\
+            feature liga{\n\
+            sub f  i by f_i ;sub f l by f_l;\n\
+            } liga;\n";
+        let expected = "languagesystem DFLT dflt;\n\
+            languagesystem latn dflt;\n\
+            @lc = [a b c];\n\
+            feature liga {\n\
+            \tsub f i by f_i;\n\
+            \tsub f l by f_l;\n\
+            } liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_nested_lookup_with_spaces() {
+        let source =
+            "feature kern {\nlookup KERN1 {\npos A V -50;\npos <10 0 20 0> ;\n} KERN1;\n} kern;";
+        let expected = "feature kern {\n  lookup KERN1 {\n    pos A V -50;\n    pos <10 0 20 0>;\n  } KERN1;\n} kern;\n";
+        assert_eq!(format_fea(source, "  "), expected);
+    }
+
+    #[test]
+    fn test_format_fea_comments_preserved() {
+        let source = "# header comment\n\nfeature liga { # trailing\n# own line\nsub f i by f_i; # after\n} liga;\n";
+        let expected = "# header comment\n\nfeature liga { # trailing\n\t# own line\n\tsub f i by f_i; # after\n} liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_comment_inside_statement() {
+        let source = "@a = [a # first\nb];\nfeature liga # why\n{\n} liga;\n";
+        let expected = "@a = [a # first\n\tb];\nfeature liga # why\n{\n} liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_contextual_marks() {
+        let source = "feature calt {\nsub a' [ b c ]' d by e;\n} calt;\n";
+        let expected = "feature calt {\n\tsub a' [b c]' d by e;\n} calt;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_multiline_statement_continuation() {
+        let source = "@caps = [A B C\n    D E F\n];\n";
+        let expected = "@caps = [A B C\n\tD E F\n];\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_blank_lines_collapsed() {
+        let source = "\n\n\nlanguagesystem DFLT dflt;\n\n\n\nfeature liga {\n\nsub f i by f_i;\n\n} liga;\n\n\n";
+        let expected = "languagesystem DFLT dflt;\n\nfeature liga {\n\tsub f i by f_i;\n} liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_anonymous_block_verbatim() {
+        let source = "anon sbit {\n  72 % bitmap size\n    { odd }   stuff\n} sbit;\nfeature liga {sub f i by f_i;} liga;\n";
+        let expected = "anon sbit {\n  72 % bitmap size\n    { odd }   stuff\n} sbit;\nfeature liga {\n\tsub f i by f_i;\n} liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_include() {
+        let source =
+            "include( ../shared/classes.fea )\nfeature liga { include(liga.fea); } liga;\n";
+        let expected =
+            "include(../shared/classes.fea)\nfeature liga {\n\tinclude(liga.fea);\n} liga;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+        assert_eq!(include_paths(source), vec!["../shared/classes.fea", "liga.fea"]);
+    }
+
+    #[test]
+    fn test_format_fea_strings_and_crlf() {
+        let source = "table name {\r\nnameid 9 \"Some  Designer\";\r\n} name;\r\n";
+        let expected = "table name {\n\tnameid 9 \"Some  Designer\";\n} name;\n";
+        assert_eq!(format_fea(source, "\t"), expected);
+    }
+
+    #[test]
+    fn test_format_fea_is_idempotent() {
+        let source = "# c\nlanguagesystem DFLT dflt;\n@a=[a b\nc];\nfeature ss01 {\nfeatureNames {\nname \"Alt\";\n};\nsub a by a.alt; # x\n} ss01;\n";
+        let once = format_fea(source, "    ");
+        let twice = format_fea(&once, "    ");
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_format_fea_empty() {
+        assert_eq!(format_fea("", "\t"), "");
+        assert_eq!(format_fea("\n\n", "\t"), "");
+    }
+
+    #[test]
+    fn test_format_fea_includes_in_place() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let include_path = tmp_dir.path().join("shared.fea");
+        let nested_path = tmp_dir.path().join("nested.fea");
+        fs::write(&include_path, "include(nested.fea);\n@a=[a  b];").unwrap();
+        fs::write(&nested_path, "@b=[c  d];").unwrap();
+
+        let res = format_fea_includes(
+            "include(shared.fea);",
+            tmp_dir.path(),
+            "\t",
+            LineEnding::Lf,
+            false,
+        );
+        assert_eq!(res.unwrap(), vec![nested_path.clone(), include_path.clone()]);
+        // include files are not modified when they are only checked
+        assert_eq!(fs::read_to_string(&nested_path).unwrap(), "@b=[c  d];");
+
+        let res =
+            format_fea_includes("include(shared.fea);", tmp_dir.path(), "\t", LineEnding::Lf, true);
+        assert_eq!(res.unwrap().len(), 2);
+        assert_eq!(
+            fs::read_to_string(&include_path).unwrap(),
+            "include(nested.fea);\n@a = [a b];\n"
+        );
+        assert_eq!(fs::read_to_string(&nested_path).unwrap(), "@b = [c d];\n");
+    }
+
+    #[test]
+    fn test_write_atomic_concurrent() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let include_path = tmp_dir.path().join("shared.fea");
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        assert!(write_atomic(&include_path, "@a = [a b];\n").is_ok());
+                    }
+                });
+            }
+        });
+        assert_eq!(fs::read_to_string(&include_path).unwrap(), "@a = [a b];\n");
+        // temporary directories are removed
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_format_fea_includes_missing_file() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let res = format_fea_includes(
+            "include(missing.fea);",
+            tmp_dir.path(),
+            "\t",
+            LineEnding::Lf,
+            true,
+        );
+        assert!(matches!(res, Err(Error::FeaRead(_, _))));
+    }
+}
@@ -1,79 +1,837 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
 
-use norad::{Font, QuoteChar, WriteOptions};
+use norad::{Font, Glyph, QuoteChar, WriteOptions};
+use rayon::prelude::*;
+use tempdir::TempDir;
 
+use crate::lib::designspace;
 use crate::lib::errors::{Error, Result};
-use crate::lib::utils;
+use crate::lib::fea;
+use crate::lib::glyphs::{self, GlyphNormalizations, IdentifierMode};
+use crate::lib::lenient::{self, LenientUfo, SkippedGlif};
+use crate::lib::line_endings::{self, LineEnding, UfoLineEndings};
+use crate::lib::lossiness::{self, Loss};
+use crate::lib::stores;
+use crate::lib::utils::{self, UfoFileType};
+use crate::lib::verify;
+
+/// ufofmt source formatting options
+#[derive(Debug, Clone)]
+pub(crate) struct FormatOptions {
+    pub(crate) singlequotes: bool,
+    pub(crate) indent_with_space: bool,
+    pub(crate) indent_number: u8,
+    /// glif file indentation string, overrides the indentation char options
+    pub(crate) glif_indent: Option<String>,
+    /// plist file indentation string, overrides the indentation char options
+    pub(crate) plist_indent: Option<String>,
+    /// designspace file indentation string, overrides the indentation char options
+    pub(crate) designspace_indent: Option<String>,
+    pub(crate) format_features: bool,
+    /// Rename glif files with the UFO 3 specification user name to file name algorithm
+    pub(crate) rename_glifs: bool,
+    /// Validate the PNG file structure of images
+    pub(crate) verify_images: bool,
+    /// Remove images that are not referenced by a glif file
+    pub(crate) remove_unused_images: bool,
+    /// Normalize the line endings of data directory text files
+    pub(crate) normalize_data: bool,
+    /// Rotate closed contours to a canonical start point
+    pub(crate) normalize_start_points: bool,
+    /// Remove on-curve points at the same position as the previous on-curve point
+    pub(crate) remove_duplicate_points: bool,
+    /// Set the type of curve and qcurve points without offcurve points to line
+    pub(crate) normalize_point_types: bool,
+    /// Add missing glyph object identifiers or strip unreferenced identifiers
+    pub(crate) identifiers: IdentifierMode,
+    /// Sort unicode values, anchors, and guidelines
+    pub(crate) sort_elements: bool,
+    /// Load the written UFO source and compare it with the formatted source
+    pub(crate) verify: bool,
+    /// Fail instead of warning when source contents would be discarded
+    pub(crate) strict: bool,
+    /// Skip glif files that cannot be parsed instead of failing the UFO source
+    pub(crate) lenient: bool,
+    /// Line endings of the written glif, plist, and feature files
+    pub(crate) line_ending: LineEnding,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            singlequotes: false,
+            indent_with_space: false,
+            indent_number: 1,
+            glif_indent: None,
+            plist_indent: None,
+            designspace_indent: None,
+            format_features: false,
+            rename_glifs: false,
+            verify_images: false,
+            remove_unused_images: false,
+            normalize_data: false,
+            normalize_start_points: false,
+            remove_duplicate_points: false,
+            normalize_point_types: false,
+            identifiers: IdentifierMode::Keep,
+            sort_elements: false,
+            verify: false,
+            strict: false,
+            lenient: false,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+/// Indentation strings by UFO source file type
+#[derive(Debug, Clone, PartialEq)]
+struct Indentation {
+    default: String,
+    glif: String,
+    plist: String,
+    designspace: String,
+}
+
+impl Indentation {
+    fn from_options(fmt_options: &FormatOptions) -> Result<Self> {
+        let default = get_indent_str(fmt_options.indent_with_space, fmt_options.indent_number)?;
+        let file_type_indent = |indent: &Option<String>| match indent {
+            Some(indent_str) => validate_indent_str(indent_str).map(|_| indent_str.clone()),
+            None => Ok(default.clone()),
+        };
+        Ok(Indentation {
+            glif: file_type_indent(&fmt_options.glif_indent)?,
+            plist: file_type_indent(&fmt_options.plist_indent)?,
+            designspace: file_type_indent(&fmt_options.designspace_indent)?,
+            default,
+        })
+    }
+}
 
-/// Read/write roundtrip through the norad library. Returns Result with successful
-/// &PathBuf path write or error
+/// Read/write roundtrip through the norad library. Returns Result with the
+/// formatting summary or error
 pub(crate) fn format_ufo(
     ufopath: &Path,
     unique_filename: &Option<String>,
     unique_extension: &Option<String>,
-    singlequotes: bool,
-    indent_with_space: bool,
-    indent_number: u8,
-) -> Result<PathBuf> {
+    out_dir: &Option<PathBuf>,
+    fmt_options: &FormatOptions,
+) -> Result<FormatSummary> {
     // validate UFO directory path request
     if !ufopath.exists() {
         return Err(Error::InvalidPath(ufopath.into()));
     }
     // define out directory path based on optional user-specified command line options
-    let outpath: PathBuf = if unique_filename.is_some() || unique_extension.is_some() {
-        utils::get_ufo_outpath(ufopath, unique_filename, unique_extension)
+    let outpath = get_outpath(ufopath, unique_filename, unique_extension, out_dir)?;
+    if designspace::is_designspace_path(ufopath) {
+        return format_designspace_file(ufopath, Some(&outpath), fmt_options);
+    }
+
+    let in_place = outpath == ufopath;
+    // in place writes replace the source files, so the written files are compared with
+    // the source file contents, and other writes are compared with the source tree
+    let source = match in_place {
+        true => utils::SourceFiles::read(ufopath, is_store_path)
+            .map_err(|e| Error::Io(ufopath.into(), e))?,
+        false => utils::SourceFiles::Dir(ufopath),
+    };
+    // include files are shared with the source tree, so they are only formatted in place
+    let includes = match in_place {
+        true => IncludeMode::Write,
+        false => IncludeMode::Skip,
+    };
+    let summary = format_ufo_at(ufopath, &outpath, fmt_options, includes)?;
+    let changed = get_changed_paths(ufopath, &source, &outpath, &summary)?;
+    Ok(FormatSummary { changed, ..summary })
+}
+
+/// OpenType feature include file handling
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IncludeMode {
+    /// Include files are not read
+    Skip,
+    /// Include files are formatted without writing them
+    Check,
+    /// Include files are formatted in place
+    Write,
+}
+
+/// Returns true for the relative paths of the data and images store files.  Store
+/// files are written without changes unless they are normalized or removed, so
+/// their contents are not compared.
+fn is_store_path(relpath: &Path) -> bool {
+    relpath.starts_with("data") || relpath.starts_with("images")
+}
+
+/// Returns the sorted paths of the source files that formatting changes: the
+/// source files that differ from the files written at `outpath`, the removed and
+/// normalized store files, and the changed include files in `summary`
+fn get_changed_paths(
+    ufopath: &Path,
+    source: &utils::SourceFiles,
+    outpath: &Path,
+    summary: &FormatSummary,
+) -> Result<Vec<PathBuf>> {
+    let changed_files = utils::get_changed_files(source, outpath, is_store_path)
+        .map_err(|e| Error::Io(outpath.into(), e))?;
+    let changed: BTreeSet<PathBuf> = changed_files
+        .into_iter()
+        .map(|f| ufopath.join(f))
+        .chain(summary.removed_images.iter().cloned())
+        .chain(summary.normalized_data.iter().cloned())
+        .chain(summary.changed.iter().cloned())
+        .collect();
+    Ok(changed.into_iter().collect())
+}
+
+/// Format the UFO source at `ufopath` and write it to `outpath`.  The changed
+/// OpenType feature include files are returned in the summary `changed` paths.
+fn format_ufo_at(
+    ufopath: &Path,
+    outpath: &Path,
+    fmt_options: &FormatOptions,
+    includes: IncludeMode,
+) -> Result<FormatSummary> {
+    // define the indentation spacing format based on user options
+    let indentation = Indentation::from_options(fmt_options)?;
+
+    let losses = scan_lossy_contents(ufopath, fmt_options)?;
+
+    // norad lib read/write round trip formatting
+    let LenientUfo { mut ufo, skipped_glifs, .. } =
+        lenient::load_ufo(ufopath, fmt_options.lenient)?;
+    // verification compares the written source with the source as it was loaded
+    let loaded = match fmt_options.verify {
+        true => Some(ufo.clone()),
+        false => None,
+    };
+    // optional OpenType feature file formatting
+    let mut changed_includes = Vec::new();
+    if fmt_options.format_features && !ufo.features.is_empty() {
+        ufo.features = fea::format_fea(&ufo.features, &indentation.default);
+        if includes != IncludeMode::Skip {
+            // include paths are relative to the directory that contains the UFO source
+            let include_dir = match ufopath.parent() {
+                Some(par) => par,
+                None => Path::new("."),
+            };
+            changed_includes = fea::format_fea_includes(
+                &ufo.features,
+                include_dir,
+                &indentation.default,
+                fmt_options.line_ending,
+                includes == IncludeMode::Write,
+            )?;
+        }
+    }
+
+    let (removed_images, normalized_data) = prepare_stores(&mut ufo, ufopath, fmt_options)?;
+    normalize_glyphs(&mut ufo, fmt_options);
+    sort_font_info_guidelines(&mut ufo, fmt_options);
+    // source line endings are detected before an in place write replaces the source files
+    let line_endings = UfoLineEndings::from_source(ufopath, fmt_options.line_ending)?;
+    // norad writes LF feature line breaks, so the converted features differ from the source
+    let converted_features = line_endings.convert_features(&ufo.features) != ufo.features;
+
+    // output directory trees are created as needed
+    if let Some(parent) = outpath.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| Error::Io(parent.into(), e))?;
+        }
+    }
+
+    write_ufo(&mut ufo, outpath, fmt_options, &indentation, &skipped_glifs)?;
+    // skipped glif files are written unchanged
+    let skipped_relpaths: Vec<PathBuf> =
+        skipped_glifs.iter().map(|g| g.layer_dir.join(&g.file_name)).collect();
+    line_endings.apply(outpath, &skipped_relpaths)?;
+    if let Some(loaded) = loaded {
+        let mut normalizations = verify::Normalizations::from_options(fmt_options);
+        normalizations.feature_line_endings = converted_features;
+        verify::verify_ufo(&loaded, outpath, fmt_options.lenient, &normalizations)?;
+    }
+    Ok(FormatSummary {
+        source: ufopath.into(),
+        outpath: outpath.into(),
+        removed_images,
+        normalized_data,
+        changed: changed_includes,
+        losses,
+        skipped_glifs,
+        ..Default::default()
+    })
+}
+
+/// Apply the data and images directory options and validate the store entries.
+/// Returns the removed image paths and the normalized data file paths.
+fn prepare_stores(
+    ufo: &mut Font,
+    ufopath: &Path,
+    fmt_options: &FormatOptions,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let removed_images = match fmt_options.remove_unused_images {
+        true => stores::remove_unused_images(ufo, ufopath),
+        false => Vec::new(),
+    };
+    let normalized_data = match fmt_options.normalize_data {
+        true => stores::normalize_data_line_endings(ufo, ufopath)?,
+        false => Vec::new(),
+    };
+    // norad removes the UFO source directory before it fails on invalid entries
+    stores::validate_stores(ufo, ufopath, fmt_options.verify_images)?;
+    Ok((removed_images, normalized_data))
+}
+
+/// Apply the optional glyph normalizations to the glyphs in all layers
+fn normalize_glyphs(ufo: &mut Font, fmt_options: &FormatOptions) {
+    let normalizations = GlyphNormalizations::from_options(fmt_options);
+    if normalizations.is_empty() {
+        return;
+    }
+    let layer_names: Vec<String> = ufo.layers.names().map(|name| name.to_string()).collect();
+    for layer_name in layer_names {
+        if let Some(layer) = ufo.layers.get_mut(&layer_name) {
+            let mut glyphs: Vec<&mut Glyph> = layer.iter_mut().collect();
+            glyphs.par_iter_mut().for_each(|glyph| normalizations.apply(glyph));
+        }
+    }
+}
+
+fn sort_font_info_guidelines(ufo: &mut Font, fmt_options: &FormatOptions) {
+    if let (true, Some(guidelines)) = (fmt_options.sort_elements, &mut ufo.font_info.guidelines) {
+        glyphs::sort_guidelines(guidelines);
+    }
+}
+
+/// Serialize a UFO source at `outpath` with the user formatting options
+fn write_ufo(
+    ufo: &mut Font,
+    outpath: &Path,
+    fmt_options: &FormatOptions,
+    indentation: &Indentation,
+    skipped_glifs: &[SkippedGlif],
+) -> Result<()> {
+    // Norad serialization formatting options
+    let options = get_write_options(fmt_options.singlequotes, &indentation.plist);
+    if indentation.glif == indentation.plist {
+        // Execute serialization with options
+        ufo.save_with_options(outpath, &options)
+            .map_err(|e| Error::NoradWrite(outpath.into(), e))?;
+    } else {
+        // norad writes all files with the same options, so the UFO source is saved
+        // with empty layers and the layer files are written with their own options
+        let mut empty_layers = ufo.layers.clone();
+        let layer_names: Vec<String> = empty_layers.names().map(|n| n.to_string()).collect();
+        for layer_name in layer_names.iter() {
+            if let Some(layer) = empty_layers.get_mut(layer_name) {
+                layer.clear();
+            }
+        }
+        let layers = std::mem::replace(&mut ufo.layers, empty_layers);
+        let saved = ufo.save_with_options(outpath, &options);
+        ufo.layers = layers;
+        saved.map_err(|e| Error::NoradWrite(outpath.into(), e))?;
+        let glif_options = get_write_options(fmt_options.singlequotes, &indentation.glif);
+        write_layer_glifs(ufo, outpath, &options, &glif_options, fmt_options.singlequotes)?;
+    }
+    // unparsable glif files are written before renames so that they are renamed too
+    lenient::restore_glifs(skipped_glifs, outpath, &options, fmt_options.singlequotes)?;
+    if fmt_options.rename_glifs {
+        rename_glifs(ufo, outpath, &options, fmt_options.singlequotes)?;
+    }
+    Ok(())
+}
+
+/// Format a UFO source and list the source files that are not written. norad
+/// writes the files of the data that it loads, so files that are not referenced
+/// by the UFO source (e.g., glif files that are not listed in contents.plist,
+/// layer directories that are not listed in layercontents.plist, and .DS_Store
+/// files) are removed when a UFO source is formatted in place.
+pub(crate) fn format_ufo_clean(
+    ufopath: &Path,
+    unique_filename: &Option<String>,
+    unique_extension: &Option<String>,
+    out_dir: &Option<PathBuf>,
+    fmt_options: &FormatOptions,
+) -> Result<FormatSummary> {
+    if !ufopath.exists() {
+        return Err(Error::InvalidPath(ufopath.into()));
+    }
+    // designspace files are single files without unwritten files
+    if designspace::is_designspace_path(ufopath) {
+        return format_ufo(ufopath, unique_filename, unique_extension, out_dir, fmt_options);
+    }
+    let source_files = utils::list_files(ufopath).map_err(|e| Error::Io(ufopath.into(), e))?;
+    let summary = format_ufo(ufopath, unique_filename, unique_extension, out_dir, fmt_options)?;
+    let written_files =
+        utils::list_files(&summary.outpath).map_err(|e| Error::Io(summary.outpath.clone(), e))?;
+    let unwritten = source_files.difference(&written_files).map(|f| ufopath.join(f)).collect();
+    Ok(FormatSummary { unwritten, ..summary })
+}
+
+/// Format a UFO source in a temporary directory without modifying the UFO
+/// source or its OpenType feature include files. Returns the source and include
+/// files that formatting changes and the source files that `format_ufo_clean`
+/// does not write.
+pub(crate) fn check_ufo(ufopath: &Path, fmt_options: &FormatOptions) -> Result<FormatSummary> {
+    if !ufopath.exists() {
+        return Err(Error::InvalidPath(ufopath.into()));
+    }
+    if designspace::is_designspace_path(ufopath) {
+        let summary = format_designspace_file(ufopath, None, fmt_options)?;
+        return Ok(FormatSummary { checked: vec![ufopath.into()], ..summary });
+    }
+    let staging_dir = TempDir::new("ufofmt").map_err(|e| Error::Io(std::env::temp_dir(), e))?;
+    let staged_path = staging_dir.path().join("staged.ufo");
+    let summary = format_ufo_at(ufopath, &staged_path, fmt_options, IncludeMode::Check)?;
+
+    // the source files are not modified, so the staged files are compared with the source tree
+    let changed =
+        get_changed_paths(ufopath, &utils::SourceFiles::Dir(ufopath), &staged_path, &summary)?;
+    let source_files = utils::list_files(ufopath).map_err(|e| Error::Io(ufopath.into(), e))?;
+    let staged_files =
+        utils::list_files(&staged_path).map_err(|e| Error::Io(staged_path.clone(), e))?;
+    let unwritten = source_files.difference(&staged_files).map(|f| ufopath.join(f)).collect();
+    let checked = source_files.iter().map(|f| ufopath.join(f)).collect();
+    Ok(FormatSummary { outpath: ufopath.into(), unwritten, changed, checked, ..summary })
+}
+
+/// Format a designspace file and write it to `outpath`.  Nothing is written
+/// when `outpath` is None.
+fn format_designspace_file(
+    path: &Path,
+    outpath: Option<&Path>,
+    fmt_options: &FormatOptions,
+) -> Result<FormatSummary> {
+    let contents = fs::read(path).map_err(|e| Error::Io(path.into(), e))?;
+    let formatted = format_file_contents(path, &contents, fmt_options)?;
+    if let Some(outpath) = outpath {
+        if let Some(parent) = outpath.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                fs::create_dir_all(parent).map_err(|e| Error::Io(parent.into(), e))?;
+            }
+        }
+        if outpath != path || formatted != contents {
+            fs::write(outpath, &formatted).map_err(|e| Error::Io(outpath.into(), e))?;
+        }
+    }
+    let changed = match formatted != contents {
+        true => vec![path.into()],
+        false => Vec::new(),
+    };
+    Ok(FormatSummary {
+        source: path.into(),
+        outpath: outpath.unwrap_or(path).into(),
+        changed,
+        ..Default::default()
+    })
+}
+
+/// Scan the UFO source for contents that formatting discards. The contents are
+/// returned for warnings, or fail the UFO source in strict mode.
+fn scan_lossy_contents(ufopath: &Path, fmt_options: &FormatOptions) -> Result<Vec<Loss>> {
+    let losses = lossiness::scan_ufo(ufopath)?;
+    if fmt_options.strict && !losses.is_empty() {
+        let msg = losses.iter().map(|loss| loss.to_string()).collect::<Vec<String>>().join("; ");
+        return Err(Error::LossyContents(ufopath.into(), msg));
+    }
+    Ok(losses)
+}
+
+/// UFO source formatting summary
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FormatSummary {
+    pub(crate) source: PathBuf,
+    pub(crate) outpath: PathBuf,
+    /// source files that are not written, only listed with --clean and --check
+    pub(crate) unwritten: Vec<PathBuf>,
+    /// images that are not referenced by a glif file
+    pub(crate) removed_images: Vec<PathBuf>,
+    /// data files with normalized line endings
+    pub(crate) normalized_data: Vec<PathBuf>,
+    /// source contents that are discarded by formatting
+    pub(crate) losses: Vec<Loss>,
+    /// glif files that are written without changes because they cannot be parsed
+    pub(crate) skipped_glifs: Vec<SkippedGlif>,
+    /// source files that formatting modifies, adds, or removes
+    pub(crate) changed: Vec<PathBuf>,
+    /// source files that are compared with the formatted files, only listed with --check
+    pub(crate) checked: Vec<PathBuf>,
+}
+
+/// Write the contents.plist and glif files of all layers in a UFO source that was
+/// saved at `outpath` with empty layers
+fn write_layer_glifs(
+    ufo: &Font,
+    outpath: &Path,
+    plist_options: &WriteOptions,
+    glif_options: &WriteOptions,
+    singlequotes: bool,
+) -> Result<()> {
+    let mut glifs = Vec::new();
+    for layer in ufo.layers.iter() {
+        let layer_path = outpath.join(layer.path());
+        let mut glyph_files = BTreeMap::new();
+        for glyph in layer.iter() {
+            // norad writes a file for each glyph in the layer contents
+            if let Some(glif_path) = layer.get_path(&glyph.name) {
+                glyph_files.insert(glyph.name.to_string(), glif_path.to_string_lossy().into());
+                glifs.push((glyph, layer_path.join(glif_path)));
+            }
+        }
+        let contents_path = layer_path.join("contents.plist");
+        let contents =
+            encode_contents_plist(&contents_path, &glyph_files, plist_options, singlequotes)?;
+        fs::write(&contents_path, contents).map_err(|e| Error::Io(contents_path, e))?;
+    }
+    glifs.par_iter().try_for_each(|(glyph, glif_path)| {
+        let glif = glyph
+            .encode_xml_with_options(glif_options)
+            .map_err(|e| Error::NoradWrite(glif_path.into(), e.into()))?;
+        fs::write(glif_path, glif).map_err(|e| Error::Io(glif_path.into(), e))
+    })
+}
+
+/// Rename the glif files of all layers in a UFO source that was saved at `outpath`
+/// with the UFO 3 specification user name to file name algorithm and rewrite the
+/// contents.plist files. File names are assigned in glyph name order so that UFO
+/// sources with the same glyph set have the same file layout.
+fn rename_glifs(
+    ufo: &Font,
+    outpath: &Path,
+    options: &WriteOptions,
+    singlequotes: bool,
+) -> Result<()> {
+    let io_err = |p: &Path, e: std::io::Error| Error::Io(p.into(), e);
+    for layer in ufo.layers.iter() {
+        let layer_path = outpath.join(layer.path());
+        let contents_path = layer_path.join("contents.plist");
+        let glyph_files: BTreeMap<String, String> = plist::from_file(&contents_path)
+            .map_err(|e| Error::NoradRead(contents_path.clone(), e.into()))?;
+
+        let mut existing = HashSet::new();
+        let mut renamed_glyph_files = BTreeMap::new();
+        for glyph_name in glyph_files.keys() {
+            let file_name = utils::user_name_to_file_name(glyph_name, &existing, "", ".glif");
+            existing.insert(file_name.to_lowercase());
+            renamed_glyph_files.insert(glyph_name.clone(), file_name);
+        }
+        if renamed_glyph_files == glyph_files {
+            continue;
+        }
+
+        // two step renames through temporary file names so that new file names
+        // never replace files that have not been renamed yet, including on
+        // case-insensitive file systems
+        let renames: Vec<(PathBuf, PathBuf, PathBuf)> = glyph_files
+            .iter()
+            .filter(|(glyph_name, file_name)| renamed_glyph_files[*glyph_name] != **file_name)
+            .enumerate()
+            .map(|(i, (glyph_name, file_name))| {
+                (
+                    layer_path.join(file_name),
+                    layer_path.join(format!("{}.ufofmt-rename-{}", file_name, i)),
+                    layer_path.join(&renamed_glyph_files[glyph_name]),
+                )
+            })
+            .collect();
+        for (old_path, tmp_path, _) in renames.iter() {
+            fs::rename(old_path, tmp_path).map_err(|e| io_err(old_path, e))?;
+        }
+        for (_, tmp_path, new_path) in renames.iter() {
+            fs::rename(tmp_path, new_path).map_err(|e| io_err(new_path, e))?;
+        }
+
+        let contents =
+            encode_contents_plist(&contents_path, &renamed_glyph_files, options, singlequotes)?;
+        fs::write(&contents_path, contents).map_err(|e| io_err(&contents_path, e))?;
+    }
+    Ok(())
+}
+
+/// Returns the write path for a UFO source based on the optional user-specified
+/// command line options
+pub(crate) fn get_outpath(
+    ufopath: &Path,
+    unique_filename: &Option<String>,
+    unique_extension: &Option<String>,
+    out_dir: &Option<PathBuf>,
+) -> Result<PathBuf> {
+    // the output directory mirrors the UFO source path relative to the working directory
+    let basepath = match out_dir {
+        Some(_) => match utils::get_ufo_relpath(ufopath) {
+            Some(relpath) => relpath,
+            None => return Err(Error::InvalidOutDirSource(ufopath.into())),
+        },
+        None => ufopath.to_path_buf(),
+    };
+    let outpath = if unique_filename.is_some() || unique_extension.is_some() {
+        utils::get_ufo_outpath(&basepath, unique_filename, unique_extension)?
     } else {
         // if the user did not specify options for custom file name or custom
         // extension, then write in place over the in path
-        ufopath.to_path_buf()
+        basepath
     };
+    match out_dir {
+        Some(dir) => Ok(dir.join(outpath)),
+        None => Ok(outpath),
+    }
+}
+
+/// Returns the UFO source paths of a batch with duplicate sources removed.
+/// Sources and output paths are compared after symbolic link and relative path
+/// resolution. Fails when distinct sources have the same output path.
+pub(crate) fn get_batch_ufopaths(
+    ufopaths: &[PathBuf],
+    unique_filename: &Option<String>,
+    unique_extension: &Option<String>,
+    out_dir: &Option<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let mut batch_ufopaths = Vec::new();
+    let mut sources = HashSet::new();
+    let mut outpaths: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    for ufopath in ufopaths {
+        // invalid sources are reported in the formatting run
+        let source = match ufopath.canonicalize() {
+            Ok(source) => source,
+            Err(_) => {
+                batch_ufopaths.push(ufopath.clone());
+                continue;
+            }
+        };
+        if !sources.insert(source) {
+            continue;
+        }
+        if let Ok(outpath) = get_outpath(ufopath, unique_filename, unique_extension, out_dir) {
+            let abs_outpath =
+                utils::get_absolute_path(&outpath).map_err(|e| Error::Io(outpath.clone(), e))?;
+            if let Some(other) = outpaths.get(&abs_outpath) {
+                return Err(Error::OutputPathCollision(
+                    other.to_path_buf(),
+                    ufopath.clone(),
+                    abs_outpath,
+                ));
+            }
+            outpaths.insert(abs_outpath, ufopath);
+        }
+        batch_ufopaths.push(ufopath.clone());
+    }
+    Ok(batch_ufopaths)
+}
 
-    // define the indentation spacing format based on user CL options
-    let indentation_str = get_indent_str(indent_with_space, indent_number);
+/// Format the contents of a single UFO source file. The file type is inferred
+/// from `filepath`, which does not need to exist on disk. Returns Result with
+/// the formatted file contents or error
+pub(crate) fn format_file_contents(
+    filepath: &Path,
+    contents: &[u8],
+    fmt_options: &FormatOptions,
+) -> Result<Vec<u8>> {
+    let filetype = match utils::get_ufo_file_type(filepath) {
+        Some(ft) => ft,
+        None => return Err(Error::UnsupportedFileType(filepath.into())),
+    };
+    let indentation = Indentation::from_options(fmt_options)?;
+    let options = match filetype {
+        UfoFileType::Glif => get_write_options(fmt_options.singlequotes, &indentation.glif),
+        _ => get_write_options(fmt_options.singlequotes, &indentation.plist),
+    };
 
-    // norad lib read/write round trip formatting
-    match Font::load(ufopath) {
-        Ok(ufo) => {
-            // optional XML declaration quote style customization
-            let quote_style = {
-                match singlequotes {
-                    true => QuoteChar::Single,
-                    false => QuoteChar::Double,
-                }
+    let formatted = match filetype {
+        UfoFileType::Features => {
+            let source = String::from_utf8_lossy(contents);
+            let formatted = match fmt_options.format_features {
+                true => fea::format_fea(&source, &indentation.default),
+                false => source.replace("\r\n", "\n"),
             };
-            // Norad serialization formatting options
-
-            let options =
-                WriteOptions::default().whitespace(indentation_str).quote_char(quote_style);
-            // Execute serialization with options
-            match ufo.save_with_options(&outpath, &options) {
-                Ok(_) => Ok(outpath),
-                Err(e) => Err(Error::NoradWrite(outpath, e)),
-            }
+            formatted.into_bytes()
+        }
+        UfoFileType::Designspace => {
+            designspace::format_designspace(contents, &indentation.designspace)
+                .map_err(|msg| Error::DesignspaceRead(filepath.into(), msg))?
+        }
+        UfoFileType::Contents => {
+            // contents.plist references glif files that are not available here,
+            // so serialize the glyph name to file name map in the same way as norad
+            let glyph_files: BTreeMap<String, String> = plist::from_bytes(contents)
+                .map_err(|e| Error::NoradRead(filepath.into(), e.into()))?;
+            encode_contents_plist(filepath, &glyph_files, &options, fmt_options.singlequotes)?
+        }
+        _ => format_file_contents_in_staging_ufo(
+            filepath,
+            contents,
+            filetype,
+            &options,
+            fmt_options,
+        )?,
+    };
+    let line_ending = line_endings::resolve_line_ending(fmt_options.line_ending, contents);
+    Ok(line_endings::convert_file_line_endings(&formatted, filetype, line_ending))
+}
+
+/// Serialize a contents.plist glyph name to file name map in the same way as norad
+pub(crate) fn encode_contents_plist(
+    filepath: &Path,
+    glyph_files: &BTreeMap<String, String>,
+    options: &WriteOptions,
+    singlequotes: bool,
+) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    plist::to_writer_xml_with_options(&mut buf, glyph_files, options.xml_options())
+        .map_err(|e| Error::NoradWrite(filepath.into(), e.into()))?;
+    if singlequotes {
+        buf = replace_xml_declaration_quotes(&buf);
+    }
+    Ok(buf)
+}
+
+/// Format glif and plist file contents through a norad read/write roundtrip in
+/// a temporary UFO so that the serialization is identical to `format_ufo`
+fn format_file_contents_in_staging_ufo(
+    filepath: &Path,
+    contents: &[u8],
+    filetype: UfoFileType,
+    options: &WriteOptions,
+    fmt_options: &FormatOptions,
+) -> Result<Vec<u8>> {
+    let staging_dir = TempDir::new("ufofmt").map_err(|e| Error::Io(std::env::temp_dir(), e))?;
+    let io_err = |p: &Path, e: std::io::Error| Error::Io(p.into(), e);
+    // file_name is guaranteed by the file type inference
+    let file_name = filepath.file_name().unwrap();
+
+    if filetype == UfoFileType::Glif {
+        let glif_path = staging_dir.path().join(file_name);
+        fs::write(&glif_path, contents).map_err(|e| io_err(&glif_path, e))?;
+        let mut glyph =
+            Glyph::load(&glif_path).map_err(|e| Error::NoradRead(filepath.into(), e))?;
+        GlyphNormalizations::from_options(fmt_options).apply(&mut glyph);
+        return glyph
+            .encode_xml_with_options(options)
+            .map_err(|e| Error::NoradWrite(filepath.into(), e.into()));
+    }
+
+    // minimal UFO source with the requested file
+    let in_ufo_path = staging_dir.path().join("in.ufo");
+    let default_layer_path = in_ufo_path.join("glyphs");
+    fs::create_dir_all(&default_layer_path).map_err(|e| io_err(&default_layer_path, e))?;
+    let staged_files = [
+        (in_ufo_path.join("metainfo.plist"), METAINFO_PLIST_MINIMAL),
+        (in_ufo_path.join("layercontents.plist"), LAYERCONTENTS_PLIST_MINIMAL),
+        (default_layer_path.join("contents.plist"), CONTENTS_PLIST_EMPTY),
+    ];
+    for (path, minimal_contents) in staged_files.iter() {
+        fs::write(path, minimal_contents).map_err(|e| io_err(path, e))?;
+    }
+    let (in_path, out_relpath) = match filetype {
+        UfoFileType::LayerInfo => {
+            (default_layer_path.join(file_name), Path::new("glyphs").join(file_name))
         }
-        Err(e) => Err(Error::NoradRead(ufopath.into(), e)),
+        _ => (in_ufo_path.join(file_name), PathBuf::from(file_name)),
+    };
+    fs::write(&in_path, contents).map_err(|e| io_err(&in_path, e))?;
+
+    if filetype == UfoFileType::LayerContents {
+        // create empty layer directories for the layers that the file defines
+        let layers: Vec<(String, PathBuf)> =
+            plist::from_bytes(contents).map_err(|e| Error::NoradRead(filepath.into(), e.into()))?;
+        for (_, layer_dir) in layers.iter() {
+            let layer_path = in_ufo_path.join(layer_dir);
+            fs::create_dir_all(&layer_path).map_err(|e| io_err(&layer_path, e))?;
+            let contents_path = layer_path.join("contents.plist");
+            fs::write(&contents_path, CONTENTS_PLIST_EMPTY)
+                .map_err(|e| io_err(&contents_path, e))?;
+        }
+    }
+
+    let mut ufo = Font::load(&in_ufo_path).map_err(|e| Error::NoradRead(filepath.into(), e))?;
+    sort_font_info_guidelines(&mut ufo, fmt_options);
+    let out_ufo_path = staging_dir.path().join("out.ufo");
+    ufo.save_with_options(&out_ufo_path, options)
+        .map_err(|e| Error::NoradWrite(filepath.into(), e))?;
+
+    // norad does not write empty plist files, leave them as they are
+    let out_path = out_ufo_path.join(out_relpath);
+    match out_path.exists() {
+        true => fs::read(&out_path).map_err(|e| io_err(&out_path, e)),
+        false => Ok(contents.to_vec()),
+    }
+}
+
+const METAINFO_PLIST_MINIMAL: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+\t<key>creator</key>
+\t<string>org.linebender.norad</string>
+\t<key>formatVersion</key>
+\t<integer>3</integer>
+</dict>
+</plist>";
+
+const LAYERCONTENTS_PLIST_MINIMAL: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<array>
+\t<array>
+\t\t<string>public.default</string>
+\t\t<string>glyphs</string>
+\t</array>
+</array>
+</plist>";
+
+const CONTENTS_PLIST_EMPTY: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict/>
+</plist>";
+
+/// Replace the XML declaration attribute double quotes with single quotes
+fn replace_xml_declaration_quotes(xml: &[u8]) -> Vec<u8> {
+    let double_quote_decl: &[u8] = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>";
+    let single_quote_decl: &[u8] = b"<?xml version='1.0' encoding='UTF-8'?>";
+    match xml.strip_prefix(double_quote_decl) {
+        Some(rest) => [single_quote_decl, rest].concat(),
+        None => xml.to_vec(),
     }
 }
 
-fn get_indent_str(indent_with_space: bool, indent_number: u8) -> &'static str {
-    match (indent_with_space, indent_number) {
-        (false, 1) => "\t",
-        (false, 2) => "\t\t",
-        (false, 3) => "\t\t\t",
-        (false, 4) => "\t\t\t\t",
-        (true, 1) => " ",
-        (true, 2) => "  ",
-        (true, 3) => "   ",
-        (true, 4) => "    ",
-        (_, _) => panic!("unsupported indentation definition"),
+fn get_write_options(singlequotes: bool, indentation_str: &str) -> WriteOptions {
+    // optional XML declaration quote style customization
+    let quote_style = {
+        match singlequotes {
+            true => QuoteChar::Single,
+            false => QuoteChar::Double,
+        }
+    };
+    WriteOptions::default().whitespace(indentation_str.to_string()).quote_char(quote_style)
+}
+
+fn get_indent_str(indent_with_space: bool, indent_number: u8) -> Result<String> {
+    let indent_char = if indent_with_space { " " } else { "\t" };
+    let indent_str = indent_char.repeat(indent_number as usize);
+    validate_indent_str(&indent_str)?;
+    Ok(indent_str)
+}
+
+/// Indentation strings must be one or more tab chars or one or more space chars
+pub(crate) fn validate_indent_str(indent_str: &str) -> Result<()> {
+    let is_valid = !indent_str.is_empty()
+        && (indent_str.chars().all(|c| c == '\t') || indent_str.chars().all(|c| c == ' '));
+    match is_valid {
+        true => Ok(()),
+        false => Err(Error::InvalidIndentation(indent_str.to_string())),
     }
 }
 
 #[cfg(test)]
+// the tests pass borrowed paths to generic path arguments
+#[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
 mod tests {
     use super::*;
 
-    use std::{fs, path::Path};
+    use std::{ffi::OsStr, fs, path::Path};
 
     use fs_extra::dir::{copy, CopyOptions};
     use pretty_assertions::assert_eq;
@@ -86,7 +844,7 @@ mod tests {
     #[test]
     fn test_format_ufo_invalid_dir_path_default() {
         let invalid_path = Path::new("totally/bogus/path/test.ufo");
-        let res = format_ufo(invalid_path, &None, &None, false, false, 1);
+        let res = format_ufo(invalid_path, &None, &None, &None, &FormatOptions::default());
         match res {
             Ok(x) => panic!("failed with unexpected test result: {:?}", x),
             Err(err) => {
@@ -107,9 +865,8 @@ mod tests {
             invalid_path,
             &Some("_new".to_string()),
             &Some(".test".to_string()),
-            false,
-            false,
-            1,
+            &None,
+            &FormatOptions::default(),
         );
         match res {
             Ok(x) => panic!("failed with unexpected test result: {:?}", x),
@@ -134,9 +891,10 @@ mod tests {
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
         // test run of formatter across valid UFO sources
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
-        assert_eq!(format!("{:?}", res_ufo_format.unwrap()), format!("{:?}", &test_ufo_path));
+        assert_eq!(res_ufo_format.unwrap().outpath, test_ufo_path);
         assert!(&test_ufo_path.exists());
     }
 
@@ -157,16 +915,617 @@ mod tests {
             &test_ufo_path,
             &Some("_new".to_string()),
             &Some("test".to_string()),
-            false,
-            false,
-            1,
+            &None,
+            &FormatOptions::default(),
         );
         assert!(res_ufo_format.is_ok());
         let expected_path = tmp_dir.path().join("MutatorSansBoldCondensed_new.test");
-        assert_eq!(format!("{:?}", res_ufo_format.unwrap()), format!("{:?}", expected_path));
+        assert_eq!(res_ufo_format.unwrap().outpath, expected_path);
         assert!(expected_path.exists() && expected_path.is_dir());
     }
 
+    #[test]
+    fn test_format_ufo_out_dir() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let out_dir = Some(tmp_dir.path().join("build/formatted"));
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let src_fontinfo = fs::read_to_string(src_ufo_path.join("fontinfo.plist")).unwrap();
+
+        let res_ufo_format =
+            format_ufo(src_ufo_path, &None, &None, &out_dir, &FormatOptions::default());
+        assert!(res_ufo_format.is_ok());
+        let expected_path =
+            tmp_dir.path().join("build/formatted/testdata/ufo/MutatorSansBoldCondensed.ufo");
+        assert_eq!(res_ufo_format.unwrap().outpath, expected_path);
+        assert!(expected_path.join("glyphs").join("A_.glif").exists());
+        // the source is not modified
+        assert_eq!(fs::read_to_string(src_ufo_path.join("fontinfo.plist")).unwrap(), src_fontinfo);
+    }
+
+    #[test]
+    fn test_format_ufo_out_ext_stale_output() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        assert!(format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default()).is_ok());
+
+        // files of an earlier output that differ from the formatted source are not changes
+        let unique_extension = Some(".formatted.ufo".to_string());
+        let outpath = get_outpath(&test_ufo_path, &None, &unique_extension, &None).unwrap();
+        fs::create_dir_all(outpath.join("glyphs")).unwrap();
+        fs::write(outpath.join("fontinfo.plist"), "stale").unwrap();
+        fs::write(outpath.join("glyphs").join("stale.glif"), "stale").unwrap();
+        let summary =
+            format_ufo(&test_ufo_path, &None, &unique_extension, &None, &FormatOptions::default())
+                .unwrap();
+        assert_eq!(summary.outpath, outpath);
+        assert!(summary.changed.is_empty(), "{:?}", summary.changed);
+        assert!(!outpath.join("glyphs").join("stale.glif").exists());
+
+        // changes are the source files that differ from the output
+        let fontinfo_path = test_ufo_path.join("fontinfo.plist");
+        let fontinfo = fs::read_to_string(&fontinfo_path).unwrap();
+        fs::write(&fontinfo_path, fontinfo + "\n").unwrap();
+        let summary =
+            format_ufo(&test_ufo_path, &None, &unique_extension, &None, &FormatOptions::default())
+                .unwrap();
+        assert_eq!(summary.changed, vec![fontinfo_path]);
+    }
+
+    #[test]
+    fn test_get_outpath() {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let out_dir = Some(PathBuf::from("build/formatted"));
+        assert_eq!(get_outpath(src_ufo_path, &None, &None, &None).unwrap(), src_ufo_path);
+        assert_eq!(
+            get_outpath(src_ufo_path, &None, &None, &out_dir).unwrap(),
+            PathBuf::from("build/formatted/testdata/ufo/MutatorSansBoldCondensed.ufo")
+        );
+        assert_eq!(
+            get_outpath(src_ufo_path, &Some("-new".to_string()), &None, &out_dir).unwrap(),
+            PathBuf::from("build/formatted/testdata/ufo/MutatorSansBoldCondensed-new.ufo")
+        );
+    }
+
+    #[test]
+    fn test_format_ufo_rename_glifs() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glyphs_path = test_ufo_path.join("glyphs");
+
+        // rename glif files with an editor specific scheme
+        let contents_path = glyphs_path.join("contents.plist");
+        let mut glyph_files: BTreeMap<String, String> = plist::from_file(&contents_path).unwrap();
+        fs::rename(glyphs_path.join("A_.glif"), glyphs_path.join("uppercase_a.glif")).unwrap();
+        fs::rename(glyphs_path.join("B_.glif"), glyphs_path.join("A_.glif")).unwrap();
+        glyph_files.insert("A".to_string(), "uppercase_a.glif".to_string());
+        glyph_files.insert("B".to_string(), "A_.glif".to_string());
+        plist::to_file_xml(&contents_path, &glyph_files).unwrap();
+
+        let fmt_options = FormatOptions { rename_glifs: true, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+
+        let glyph_files: BTreeMap<String, String> = plist::from_file(&contents_path).unwrap();
+        assert_eq!(glyph_files["A"], "A_.glif");
+        assert_eq!(glyph_files["B"], "B_.glif");
+        assert!(!glyphs_path.join("uppercase_a.glif").exists());
+        assert!(fs::read_to_string(glyphs_path.join("A_.glif")).unwrap().contains("name=\"A\""));
+        assert!(fs::read_to_string(glyphs_path.join("B_.glif")).unwrap().contains("name=\"B\""));
+        let expected_glif = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(glyphs_path.join("A_.glif")).unwrap(), expected_glif);
+        // every glif file is listed in contents.plist
+        let glif_count = fs::read_dir(&glyphs_path)
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension() == Some(OsStr::new("glif")))
+            .count();
+        assert_eq!(glif_count, glyph_files.len());
+
+        // the formatted contents.plist is identical to the default formatting
+        let expected_contents = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/contents.plist",
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&contents_path).unwrap(), expected_contents);
+    }
+
+    #[test]
+    fn test_format_ufo_clean() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let stray_files = [
+            test_ufo_path.join(".DS_Store"),
+            test_ufo_path.join("glyphs").join("stray.glif"),
+            test_ufo_path.join("glyphs.stray").join("A_.glif"),
+        ];
+        fs::create_dir(test_ufo_path.join("glyphs.stray")).unwrap();
+        for stray_file in stray_files.iter() {
+            fs::write(stray_file, "").unwrap();
+        }
+
+        // dry runs do not modify the UFO source
+        let res_dry_run = check_ufo(&test_ufo_path, &FormatOptions::default());
+        let dry_run_summary = res_dry_run.unwrap();
+        assert_eq!(dry_run_summary.unwritten, stray_files.to_vec());
+        assert!(stray_files.iter().all(|f| f.exists()));
+        let glif_str = fs::read_to_string(test_ufo_path.join("glyphs").join("A_.glif")).unwrap();
+        assert!(glif_str.contains("\n  <advance"));
+
+        let res_clean =
+            format_ufo_clean(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
+        let clean_summary = res_clean.unwrap();
+        assert_eq!(clean_summary.outpath, test_ufo_path);
+        assert_eq!(clean_summary.unwritten, stray_files.to_vec());
+        assert!(stray_files.iter().all(|f| !f.exists()));
+
+        // formatted UFO sources do not have files to clean
+        let res_dry_run = check_ufo(&test_ufo_path, &FormatOptions::default());
+        assert!(res_dry_run.unwrap().unwritten.is_empty());
+    }
+
+    #[test]
+    fn test_check_ufo() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+
+        // checks do not modify the UFO source
+        let check_summary = check_ufo(&test_ufo_path, &FormatOptions::default()).unwrap();
+        assert_eq!(check_summary.outpath, test_ufo_path);
+        assert!(check_summary.changed.contains(&glif_path));
+        assert!(check_summary.unwritten.is_empty());
+        assert_eq!(check_summary.checked.len(), utils::list_files(&test_ufo_path).unwrap().len());
+        assert_eq!(fs::read_to_string(&glif_path).unwrap(), glif_str);
+
+        // the same files are changed by formatting
+        let summary =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default()).unwrap();
+        assert_eq!(summary.changed, check_summary.changed);
+        let res_check = check_ufo(&test_ufo_path, &FormatOptions::default());
+        assert!(res_check.unwrap().changed.is_empty());
+        let summary =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default()).unwrap();
+        assert!(summary.changed.is_empty());
+    }
+
+    #[test]
+    fn test_format_ufo_line_endings() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let fmt_options = |line_ending| FormatOptions {
+            format_features: true,
+            line_ending,
+            verify: true,
+            ..Default::default()
+        };
+
+        let res_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options(LineEnding::Crlf));
+        assert!(res_format.is_ok());
+        for relpath in &["glyphs/A_.glif", "fontinfo.plist", "features.fea"] {
+            let contents = fs::read(test_ufo_path.join(relpath)).unwrap();
+            assert_eq!(line_endings::detect_line_ending(&contents), Some(LineEnding::Crlf));
+            assert!(!String::from_utf8(contents).unwrap().replace("\r\n", "").contains('\n'));
+        }
+
+        // checks honour the line ending policy
+        let res_check = check_ufo(&test_ufo_path, &fmt_options(LineEnding::Crlf));
+        assert!(res_check.unwrap().changed.is_empty());
+        let res_check = check_ufo(&test_ufo_path, &fmt_options(LineEnding::Preserve));
+        assert!(res_check.unwrap().changed.is_empty());
+        let res_check = check_ufo(&test_ufo_path, &fmt_options(LineEnding::Lf));
+        assert!(res_check.unwrap().changed.contains(&test_ufo_path.join("glyphs/A_.glif")));
+
+        // preserve keeps the line endings of each source file
+        let lf_glif_path = test_ufo_path.join("glyphs/B_.glif");
+        let contents = fs::read(&lf_glif_path).unwrap();
+        let lf_contents = line_endings::convert_line_endings(&contents, LineEnding::Lf);
+        fs::write(&lf_glif_path, &lf_contents).unwrap();
+        let res_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options(LineEnding::Preserve));
+        assert!(res_format.unwrap().changed.is_empty());
+        assert_eq!(fs::read(&lf_glif_path).unwrap(), lf_contents);
+    }
+
+    #[test]
+    fn test_format_ufo_line_endings_text_contents() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs/A_.glif");
+        let glif_str = fs::read_to_string(&glif_path).unwrap().replacen(
+            "  <advance",
+            "  <note>line 1\nline 2</note>\n  <advance",
+            1,
+        );
+        fs::write(&glif_path, glif_str).unwrap();
+        fs::write(test_ufo_path.join("features.fea"), "a;\nb;\n").unwrap();
+        let fmt_options =
+            FormatOptions { line_ending: LineEnding::Crlf, verify: true, ..Default::default() };
+
+        // line breaks in text contents are not converted
+        let res_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_format.is_ok());
+        let contents = fs::read_to_string(&glif_path).unwrap();
+        assert!(contents.contains("<note>line 1\nline 2</note>\r\n"));
+        let ufo = Font::load(&test_ufo_path).unwrap();
+        let glyph = ufo.get_glyph("A").unwrap();
+        assert_eq!(glyph.note, Some("line 1\nline 2".to_string()));
+        assert_eq!(ufo.features, "a;\r\nb;\r\n");
+        assert!(check_ufo(&test_ufo_path, &fmt_options).unwrap().changed.is_empty());
+
+        // converted features pass verification
+        fs::write(test_ufo_path.join("features.fea"), "a;\nb;\n").unwrap();
+        let summary = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options).unwrap();
+        assert_eq!(summary.changed, vec![test_ufo_path.join("features.fea")]);
+    }
+
+    #[test]
+    fn test_format_file_contents_line_endings() {
+        let filepath = Path::new("features.fea");
+        let fmt_options = |line_ending| FormatOptions { line_ending, ..Default::default() };
+        let res = format_file_contents(filepath, b"a;\r\nb;\r\n", &fmt_options(LineEnding::Lf));
+        assert_eq!(res.unwrap(), b"a;\nb;\n");
+        let res = format_file_contents(filepath, b"a;\nb;\n", &fmt_options(LineEnding::Crlf));
+        assert_eq!(res.unwrap(), b"a;\r\nb;\r\n");
+        let res =
+            format_file_contents(filepath, b"a;\r\nb;\r\n", &fmt_options(LineEnding::Preserve));
+        assert_eq!(res.unwrap(), b"a;\r\nb;\r\n");
+    }
+
+    #[test]
+    fn test_format_ufo_clean_invalid_path() {
+        let bogus_path = Path::new("totally/bogus/path/test.ufo");
+        let fmt_options = FormatOptions::default();
+        let res_clean = format_ufo_clean(bogus_path, &None, &None, &None, &fmt_options);
+        assert!(matches!(res_clean, Err(Error::InvalidPath(_))));
+        assert!(matches!(check_ufo(bogus_path, &fmt_options), Err(Error::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_format_ufo_data_and_images() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let images_path = test_ufo_path.join("images");
+        let data_path = test_ufo_path.join("data");
+        fs::create_dir(&images_path).unwrap();
+        fs::create_dir(&data_path).unwrap();
+        let unused_image_path = images_path.join("unused.png");
+        fs::write(&unused_image_path, b"\x89PNG\r\n\x1a\n").unwrap();
+        fs::write(data_path.join("a.txt"), "one\r\ntwo\r\n").unwrap();
+
+        // invalid images do not remove the UFO source
+        let fmt_options = FormatOptions { verify_images: true, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(matches!(res_ufo_format, Err(Error::InvalidImage(p, _)) if p == unused_image_path));
+        assert!(unused_image_path.exists());
+        assert!(test_ufo_path.join("glyphs").join("A_.glif").exists());
+
+        let fmt_options = FormatOptions {
+            verify_images: true,
+            remove_unused_images: true,
+            normalize_data: true,
+            ..Default::default()
+        };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        let summary = res_ufo_format.unwrap();
+        assert_eq!(summary.removed_images, vec![unused_image_path.clone()]);
+        assert_eq!(summary.normalized_data, vec![data_path.join("a.txt")]);
+        assert!(!unused_image_path.exists());
+        assert_eq!(fs::read_to_string(data_path.join("a.txt")).unwrap(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_format_ufo_glyph_normalizations() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+        // rotate the first contour and add a duplicate point
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+        let glif_str = glif_str.replacen(
+            "<point x=\"-10\" y=\"0\" type=\"line\"/>\n      <point x=\"250\" y=\"0\" type=\"line\"/>",
+            "<point x=\"250\" y=\"0\" type=\"curve\"/>\n      <point x=\"250\" y=\"0\" type=\"line\"/>",
+            1,
+        );
+        let glif_str = glif_str.replacen(
+            "<point x=\"104\" y=\"800\" type=\"line\"/>",
+            "<point x=\"104\" y=\"800\" type=\"line\"/>\n      <point x=\"-10\" y=\"0\" type=\"line\"/>",
+            1,
+        );
+        assert!(glif_str.contains("<point x=\"250\" y=\"0\" type=\"curve\"/>"));
+        assert!(
+            glif_str.contains("<point x=\"104\" y=\"800\" type=\"line\"/>\n      <point x=\"-10\"")
+        );
+        fs::write(&glif_path, glif_str).unwrap();
+
+        let fmt_options = FormatOptions {
+            normalize_start_points: true,
+            remove_duplicate_points: true,
+            normalize_point_types: true,
+            ..Default::default()
+        };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+        let expected_glif = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&glif_path).unwrap(), expected_glif);
+
+        // single file formatting applies the same normalizations
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+        let rotated = glif_str.replacen(
+            "<point x=\"-10\" y=\"0\" type=\"line\"/>\n\t\t\t<point x=\"250\" y=\"0\" type=\"line\"/>",
+            "<point x=\"250\" y=\"0\" type=\"line\"/>",
+            1,
+        );
+        let rotated = rotated.replacen(
+            "<point x=\"104\" y=\"800\" type=\"line\"/>",
+            "<point x=\"104\" y=\"800\" type=\"line\"/>\n\t\t\t<point x=\"-10\" y=\"0\" type=\"line\"/>",
+            1,
+        );
+        assert_ne!(rotated, glif_str);
+        let res = format_file_contents(&glif_path, rotated.as_bytes(), &fmt_options);
+        assert_eq!(String::from_utf8(res.unwrap()).unwrap(), expected_glif);
+    }
+
+    #[test]
+    fn test_format_ufo_identifiers() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let mut glif_strs = Vec::new();
+        for copy_dir in &["one", "two"] {
+            let copy_path = tmp_dir.path().join(copy_dir);
+            fs::create_dir(&copy_path).unwrap();
+            let res_ufo_copy = copy(src_ufo_path, &copy_path, &CopyOptions::new());
+            assert!(res_ufo_copy.is_ok());
+            let test_ufo_path = copy_path.join("MutatorSansBoldCondensed.ufo");
+            let fmt_options =
+                FormatOptions { identifiers: IdentifierMode::Add, ..Default::default() };
+            let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+            assert!(res_ufo_format.is_ok());
+            glif_strs.push(fs::read_to_string(test_ufo_path.join("glyphs/A_.glif")).unwrap());
+        }
+        assert!(glif_strs[0].contains("<contour identifier=\""));
+        assert!(glif_strs[0].contains("type=\"line\" identifier=\""));
+        // identifiers are deterministic
+        assert_eq!(glif_strs[0], glif_strs[1]);
+
+        let test_ufo_path = tmp_dir.path().join("one").join("MutatorSansBoldCondensed.ufo");
+        let fmt_options =
+            FormatOptions { identifiers: IdentifierMode::Strip, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+        let expected_glif = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(test_ufo_path.join("glyphs/A_.glif")).unwrap(),
+            expected_glif
+        );
+    }
+
+    #[test]
+    fn test_format_ufo_sort_elements() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+        let glif_str = glif_str.replacen(
+            "<unicode hex=\"0041\"/>",
+            "<unicode hex=\"0041\"/>\n  <unicode hex=\"00C1\"/>\n  <unicode hex=\"0061\"/>\n  \
+             <anchor x=\"10\" y=\"0\" name=\"top\"/>\n  <anchor x=\"0\" y=\"0\" name=\"bottom\"/>\n  \
+             <guideline y=\"500\"/>\n  <guideline x=\"20\"/>",
+            1,
+        );
+        fs::write(&glif_path, glif_str).unwrap();
+        let fontinfo_path = test_ufo_path.join("fontinfo.plist");
+        let fontinfo_str = fs::read_to_string(&fontinfo_path).unwrap();
+        let fontinfo_str = fontinfo_str.replacen(
+            "<key>guidelines</key>\n    <array/>",
+            "<key>guidelines</key>\n    <array>\n      \
+             <dict><key>y</key><integer>500</integer></dict>\n      \
+             <dict><key>x</key><integer>20</integer></dict>\n    </array>",
+            1,
+        );
+        fs::write(&fontinfo_path, &fontinfo_str).unwrap();
+
+        let fmt_options = FormatOptions { sort_elements: true, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+        let expected_elements = [
+            "\t<unicode hex=\"0041\"/>\n\t<unicode hex=\"0061\"/>\n\t<unicode hex=\"00C1\"/>\n",
+            "\t<anchor name=\"bottom\" x=\"0\" y=\"0\"/>\n\t<anchor name=\"top\" x=\"10\" y=\"0\"/>\n",
+            "\t<guideline x=\"20\"/>\n\t<guideline y=\"500\"/>\n",
+        ];
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+        for elements in expected_elements.iter() {
+            assert!(glif_str.contains(elements), "{}", glif_str);
+        }
+
+        // fontinfo guidelines are sorted in UFO sources and single files
+        let formatted_fontinfo = fs::read_to_string(&fontinfo_path).unwrap();
+        let x_index = formatted_fontinfo.find("<key>x</key>").unwrap();
+        let y_index = formatted_fontinfo.find("<key>y</key>").unwrap();
+        assert!(x_index < y_index);
+        let res = format_file_contents(&fontinfo_path, fontinfo_str.as_bytes(), &fmt_options);
+        assert_eq!(String::from_utf8(res.unwrap()).unwrap(), formatted_fontinfo);
+    }
+
+    #[test]
+    fn test_format_ufo_verify() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let fmt_options = FormatOptions {
+            indent_with_space: true,
+            indent_number: 2,
+            glif_indent: Some("\t".to_string()),
+            rename_glifs: true,
+            normalize_start_points: true,
+            identifiers: IdentifierMode::Add,
+            sort_elements: true,
+            verify: true,
+            ..Default::default()
+        };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok(), "{:?}", res_ufo_format);
+    }
+
+    #[test]
+    fn test_format_ufo_lossy_contents() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let lib_path = test_ufo_path.join("lib.plist");
+        let lib_str = fs::read_to_string(&lib_path).unwrap();
+        fs::write(&lib_path, lib_str.replacen("<dict>", "<dict>\n<!-- private -->", 1)).unwrap();
+
+        // strict mode fails without changes to the UFO source
+        let fmt_options = FormatOptions { strict: true, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(matches!(res_ufo_format, Err(Error::LossyContents(p, _)) if p == test_ufo_path));
+        assert!(fs::read_to_string(&lib_path).unwrap().contains("<!-- private -->"));
+        let res_dry_run = check_ufo(&test_ufo_path, &fmt_options);
+        assert!(matches!(res_dry_run, Err(Error::LossyContents(_, _))));
+
+        // the discarded contents are listed in the summary by default
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
+        let losses = res_ufo_format.unwrap().losses;
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].path, lib_path);
+        assert!(!fs::read_to_string(&lib_path).unwrap().contains("<!-- private -->"));
+    }
+
+    #[test]
+    fn test_format_ufo_lenient() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs").join("B_.glif");
+        let glif_str = "<glyph name=\"B\" format=\"2\"><outline>";
+        fs::write(&glif_path, glif_str).unwrap();
+
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
+        assert!(matches!(res_ufo_format, Err(Error::NoradRead(_, _))));
+
+        let fmt_options = FormatOptions { lenient: true, ..Default::default() };
+        let res_dry_run = check_ufo(&test_ufo_path, &fmt_options).unwrap();
+        assert!(res_dry_run.unwritten.is_empty());
+        assert_eq!(res_dry_run.skipped_glifs.len(), 1);
+
+        let summary = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options).unwrap();
+        assert_eq!(summary.skipped_glifs.len(), 1);
+        assert_eq!(summary.skipped_glifs[0].glyph_name, "B");
+        // the unparsable glif file is not modified, and the other glif files are formatted
+        assert_eq!(fs::read_to_string(&glif_path).unwrap(), glif_str);
+        let expected_glif = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(test_ufo_path.join("glyphs").join("A_.glif")).unwrap(),
+            expected_glif
+        );
+        let expected_contents = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/contents.plist",
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(test_ufo_path.join("glyphs").join("contents.plist")).unwrap(),
+            expected_contents
+        );
+    }
+
+    #[test]
+    fn test_get_batch_ufopaths_duplicates() {
+        let ufopaths = vec![
+            PathBuf::from("testdata/ufo/MutatorSansBoldCondensed.ufo"),
+            PathBuf::from("./testdata/ufo/../ufo/MutatorSansBoldCondensed.ufo"),
+            PathBuf::from("totally/bogus/path/test.ufo"),
+        ];
+        let res = get_batch_ufopaths(&ufopaths, &Some("-new".to_string()), &None, &None);
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                PathBuf::from("testdata/ufo/MutatorSansBoldCondensed.ufo"),
+                PathBuf::from("totally/bogus/path/test.ufo")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_batch_ufopaths_collision() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let ufo_path = tmp_dir.path().join("Font.ufo");
+        let other_ufo_path = tmp_dir.path().join("Font.ufo3");
+        fs::create_dir(&ufo_path).unwrap();
+        fs::create_dir(&other_ufo_path).unwrap();
+        let ufopaths = vec![ufo_path.clone(), other_ufo_path.clone()];
+        // distinct sources with distinct output paths
+        assert_eq!(
+            get_batch_ufopaths(&ufopaths, &Some("-new".to_string()), &None, &None).unwrap(),
+            ufopaths
+        );
+        // distinct sources with the same output path
+        let res = get_batch_ufopaths(&ufopaths, &None, &Some("test".to_string()), &None);
+        match res {
+            Err(Error::OutputPathCollision(p1, p2, outp)) => {
+                assert_eq!(p1, ufo_path);
+                assert_eq!(p2, other_ufo_path);
+                assert_eq!(outp, tmp_dir.path().canonicalize().unwrap().join("Font.test"));
+            }
+            _ => panic!("expected an output path collision error"),
+        }
+    }
+
+    #[test]
+    fn test_get_outpath_out_dir_source_outside_working_dir() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let test_ufo_path = tmp_dir.path().join("test.ufo");
+        fs::create_dir(&test_ufo_path).unwrap();
+        let res = get_outpath(&test_ufo_path, &None, &None, &Some(PathBuf::from("out")));
+        assert!(matches!(res, Err(Error::InvalidOutDirSource(_))));
+    }
+
     // ~~~~~~~~~~~~~~~~~~~~
     // Serialization format
     // ~~~~~~~~~~~~~~~~~~~~
@@ -181,7 +1540,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // glif file
@@ -245,7 +1605,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // fontinfo.plist
@@ -336,7 +1697,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // groups.plist
@@ -375,7 +1737,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // kerning.plist
@@ -605,7 +1968,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // layercontents.plist
@@ -639,7 +2003,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // lib.plist
@@ -929,7 +2294,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // metainfo.plist
@@ -959,7 +2325,8 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 1);
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
         assert!(res_ufo_format.is_ok());
 
         // glyphs/contents.plist
@@ -1083,7 +2450,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, true, false, 1);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { singlequotes: true, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
         let test_glyph_string =
             fs::read_to_string(&test_ufo_path.join("glyphs").join("A_.glif")).unwrap();
@@ -1100,7 +2473,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, true, false, 1);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { singlequotes: true, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
         let test_fontinfo_string =
             fs::read_to_string(&test_ufo_path.join("fontinfo.plist")).unwrap();
@@ -1117,7 +2496,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, true, false, 1);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { singlequotes: true, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
         let test_fontinfo_string = fs::read_to_string(&test_ufo_path.join("lib.plist")).unwrap();
         // should use single quotes
@@ -1127,14 +2512,14 @@ mod tests {
     // Indentation spacing format tests
     #[test]
     fn test_get_indent_str() {
-        let onetab = get_indent_str(false, 1);
-        let twotabs = get_indent_str(false, 2);
-        let threetabs = get_indent_str(false, 3);
-        let fourtabs = get_indent_str(false, 4);
-        let onespace = get_indent_str(true, 1);
-        let twospaces = get_indent_str(true, 2);
-        let threespaces = get_indent_str(true, 3);
-        let fourspaces = get_indent_str(true, 4);
+        let onetab = get_indent_str(false, 1).unwrap();
+        let twotabs = get_indent_str(false, 2).unwrap();
+        let threetabs = get_indent_str(false, 3).unwrap();
+        let fourtabs = get_indent_str(false, 4).unwrap();
+        let onespace = get_indent_str(true, 1).unwrap();
+        let twospaces = get_indent_str(true, 2).unwrap();
+        let threespaces = get_indent_str(true, 3).unwrap();
+        let fourspaces = get_indent_str(true, 4).unwrap();
 
         assert_eq!(onetab, "\t");
         assert_eq!(twotabs, "\t\t");
@@ -1146,6 +2531,144 @@ mod tests {
         assert_eq!(fourspaces, "    ");
     }
 
+    #[test]
+    fn test_get_indent_str_arbitrary_width() {
+        assert_eq!(get_indent_str(false, 6).unwrap(), "\t\t\t\t\t\t");
+        assert_eq!(get_indent_str(true, 8).unwrap(), "        ");
+    }
+
+    #[test]
+    fn test_get_indent_str_unsupported() {
+        assert!(
+            matches!(get_indent_str(false, 0), Err(Error::InvalidIndentation(s)) if s.is_empty())
+        );
+        assert!(
+            matches!(get_indent_str(true, 0), Err(Error::InvalidIndentation(s)) if s.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_validate_indent_str() {
+        assert!(validate_indent_str("\t").is_ok());
+        assert!(validate_indent_str("  ").is_ok());
+        assert!(matches!(validate_indent_str(""), Err(Error::InvalidIndentation(_))));
+        assert!(matches!(validate_indent_str(" \t"), Err(Error::InvalidIndentation(_))));
+        assert!(matches!(validate_indent_str("--"), Err(Error::InvalidIndentation(_))));
+    }
+
+    #[test]
+    fn test_indentation_from_options() {
+        let fmt_options = FormatOptions {
+            indent_with_space: true,
+            indent_number: 4,
+            glif_indent: Some("\t".to_string()),
+            designspace_indent: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Indentation::from_options(&fmt_options).unwrap(),
+            Indentation {
+                default: "    ".to_string(),
+                glif: "\t".to_string(),
+                plist: "    ".to_string(),
+                designspace: "  ".to_string(),
+            }
+        );
+        let fmt_options =
+            FormatOptions { plist_indent: Some("\t ".to_string()), ..Default::default() };
+        assert!(matches!(
+            Indentation::from_options(&fmt_options),
+            Err(Error::InvalidIndentation(s)) if s == "\t "
+        ));
+    }
+
+    #[test]
+    fn test_format_ufo_per_file_type_indentation() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+
+        let fmt_options = FormatOptions {
+            glif_indent: Some("\t".to_string()),
+            plist_indent: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+
+        let glif_str = fs::read_to_string(test_ufo_path.join("glyphs").join("A_.glif")).unwrap();
+        assert!(glif_str.contains("\n\t<advance"));
+        let support_glif_str =
+            fs::read_to_string(test_ufo_path.join("glyphs.background").join("S_.closed.glif"))
+                .unwrap();
+        assert!(support_glif_str.contains("\n\t<advance"));
+        let fontinfo_str = fs::read_to_string(test_ufo_path.join("fontinfo.plist")).unwrap();
+        assert!(fontinfo_str.contains("\n  <key>ascender</key>"));
+        assert!(!fontinfo_str.contains('\t'));
+        let contents_str =
+            fs::read_to_string(test_ufo_path.join("glyphs").join("contents.plist")).unwrap();
+        assert!(contents_str.contains("\n  <key>A</key>"));
+        // layer plist files are written as when all files use the plist indentation
+        let plist_out_dir = Some(tmp_dir.path().join("plist"));
+        let plist_options =
+            FormatOptions { plist_indent: Some("  ".to_string()), ..Default::default() };
+        assert!(format_ufo(src_ufo_path, &None, &None, &plist_out_dir, &plist_options).is_ok());
+        let plist_ufo_path = get_outpath(src_ufo_path, &None, &None, &plist_out_dir).unwrap();
+        for layer_dir in ["glyphs", "glyphs.background"].iter() {
+            assert_eq!(
+                fs::read(test_ufo_path.join(layer_dir).join("contents.plist")).unwrap(),
+                fs::read(plist_ufo_path.join(layer_dir).join("contents.plist")).unwrap(),
+            );
+        }
+
+        // formatting is idempotent
+        let formatted_glif = fs::read(test_ufo_path.join("glyphs").join("A_.glif")).unwrap();
+        assert!(format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options).is_ok());
+        assert_eq!(fs::read(test_ufo_path.join("glyphs").join("A_.glif")).unwrap(), formatted_glif);
+    }
+
+    #[test]
+    fn test_format_file_contents_per_file_type_indentation() {
+        let fmt_options = FormatOptions {
+            glif_indent: Some("   ".to_string()),
+            plist_indent: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let glif_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo/glyphs/A_.glif");
+        let res = format_file_contents(glif_path, &fs::read(glif_path).unwrap(), &fmt_options);
+        assert!(String::from_utf8(res.unwrap()).unwrap().contains("\n   <advance"));
+        let plist_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo/fontinfo.plist");
+        let res = format_file_contents(plist_path, &fs::read(plist_path).unwrap(), &fmt_options);
+        assert!(String::from_utf8(res.unwrap()).unwrap().contains("\n  <key>ascender</key>"));
+    }
+
+    #[test]
+    fn test_format_ufo_unsupported_indentation() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+
+        let fmt_options = FormatOptions { indent_number: 0, ..Default::default() };
+        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options);
+        assert!(matches!(res_ufo_format, Err(Error::InvalidIndentation(_))));
+    }
+
+    #[test]
+    fn test_format_ufo_without_directory_name() {
+        let res_ufo_format = format_ufo(
+            Path::new("/"),
+            &Some("-new".to_string()),
+            &None,
+            &None,
+            &FormatOptions::default(),
+        );
+        assert!(matches!(res_ufo_format, Err(Error::InvalidUfoPath(_))));
+    }
+
     #[test]
     fn test_format_indent_twotab_glif() {
         let tmp_dir = tempdir::TempDir::new("test").unwrap();
@@ -1155,7 +2678,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // glif file
@@ -1218,7 +2747,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 1);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // glif file
@@ -1282,7 +2817,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // glif file
@@ -1346,7 +2887,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 3);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 3, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // fontinfo.plist
@@ -1437,7 +2984,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // fontinfo.plist
@@ -1528,7 +3081,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // groups.plist
@@ -1567,7 +3126,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // groups.plist
@@ -1606,7 +3171,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // kerning.plist
@@ -1836,7 +3407,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // kerning.plist
@@ -2066,7 +3643,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // layercontents.plist
@@ -2100,7 +3683,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // layercontents.plist
@@ -2134,7 +3723,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // lib.plist
@@ -2424,7 +4019,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // lib.plist
@@ -2714,7 +4315,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // metainfo.plist
@@ -2744,7 +4351,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // metainfo.plist
@@ -2774,7 +4387,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, false, 2);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_number: 2, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // glyphs/contents.plist
@@ -2897,7 +4516,13 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, false, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions { indent_with_space: true, indent_number: 4, ..Default::default() },
+        );
         assert!(res_ufo_format.is_ok());
 
         // glyphs/contents.plist
@@ -3020,7 +4645,18 @@ mod tests {
         assert!(res_ufo_copy.is_ok());
         let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
 
-        let res_ufo_format = format_ufo(&test_ufo_path, &None, &None, true, true, 4);
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions {
+                singlequotes: true,
+                indent_with_space: true,
+                indent_number: 4,
+                ..Default::default()
+            },
+        );
         assert!(res_ufo_format.is_ok());
 
         // metainfo.plist
@@ -3040,4 +4676,221 @@ mod tests {
         // observed vs. expected string tests
         assert_eq!(expected_mi_string, test_mi_string);
     }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // OpenType feature file format
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+    #[test]
+    fn test_format_fea_with_include() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let copy_opt = CopyOptions::new();
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &copy_opt);
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        fs::write(
+            test_ufo_path.join("features.fea"),
+            "include(shared.fea);\nfeature liga {\nsub f i by f_i;\n} liga;",
+        )
+        .unwrap();
+        fs::write(tmp_dir.path().join("shared.fea"), "@a=[a  b];").unwrap();
+
+        let res_ufo_format = format_ufo(
+            &test_ufo_path,
+            &None,
+            &None,
+            &None,
+            &FormatOptions {
+                indent_with_space: true,
+                indent_number: 2,
+                format_features: true,
+                ..Default::default()
+            },
+        );
+        assert!(res_ufo_format.is_ok());
+
+        let test_fea_string = fs::read_to_string(test_ufo_path.join("features.fea")).unwrap();
+        assert_eq!(
+            test_fea_string,
+            "include(shared.fea);\nfeature liga {\n  sub f i by f_i;\n} liga;\n"
+        );
+        let test_include_string = fs::read_to_string(tmp_dir.path().join("shared.fea")).unwrap();
+        assert_eq!(test_include_string, "@a = [a b];\n");
+    }
+
+    #[test]
+    fn test_format_fea_include_not_modified_with_outpath() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        fs::write(test_ufo_path.join("features.fea"), "include(shared.fea);").unwrap();
+        let include_path = tmp_dir.path().join("shared.fea");
+        fs::write(&include_path, "@a=[a  b];").unwrap();
+
+        let fmt_options = FormatOptions { format_features: true, ..Default::default() };
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &Some("fmt.ufo".to_string()), &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &Some("-fmt".to_string()), &None, &None, &fmt_options);
+        assert!(res_ufo_format.is_ok());
+        assert_eq!(fs::read_to_string(&include_path).unwrap(), "@a=[a  b];");
+    }
+
+    #[test]
+    fn test_check_ufo_fea_includes() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let fmt_options = FormatOptions { format_features: true, ..Default::default() };
+        // the UFO source is formatted, so only the include file changes
+        assert!(format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options).is_ok());
+        fs::write(test_ufo_path.join("features.fea"), "include(shared.fea);\n").unwrap();
+        let include_path = tmp_dir.path().join("shared.fea");
+        fs::write(&include_path, "@a=[a  b];").unwrap();
+
+        let check_summary = check_ufo(&test_ufo_path, &fmt_options).unwrap();
+        assert_eq!(check_summary.changed, vec![include_path.clone()]);
+        assert_eq!(fs::read_to_string(&include_path).unwrap(), "@a=[a  b];");
+
+        let summary = format_ufo(&test_ufo_path, &None, &None, &None, &fmt_options).unwrap();
+        assert_eq!(summary.changed, check_summary.changed);
+        assert_eq!(fs::read_to_string(&include_path).unwrap(), "@a = [a b];\n");
+        assert!(check_ufo(&test_ufo_path, &fmt_options).unwrap().changed.is_empty());
+    }
+
+    #[test]
+    fn test_format_designspace_file() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let ds_path = tmp_dir.path().join("Font.designspace");
+        let source = "<?xml version='1.0' encoding='UTF-8'?>\n<designspace format=\"4.1\">\n<axes>\n<axis tag=\"wght\" name=\"Weight\"/>\n</axes>\n</designspace>\n";
+        fs::write(&ds_path, source).unwrap();
+        let fmt_options =
+            FormatOptions { designspace_indent: Some("  ".to_string()), ..Default::default() };
+
+        // checks do not modify the designspace file
+        let check_summary = check_ufo(&ds_path, &fmt_options).unwrap();
+        assert_eq!(check_summary.changed, vec![ds_path.clone()]);
+        assert_eq!(check_summary.checked, vec![ds_path.clone()]);
+        assert_eq!(fs::read_to_string(&ds_path).unwrap(), source);
+
+        let summary = format_ufo(&ds_path, &None, &None, &None, &fmt_options).unwrap();
+        assert_eq!(summary.changed, vec![ds_path.clone()]);
+        assert_eq!(
+            fs::read_to_string(&ds_path).unwrap(),
+            "<?xml version='1.0' encoding='UTF-8'?>\n<designspace format=\"4.1\">\n  <axes>\n    <axis tag=\"wght\" name=\"Weight\"/>\n  </axes>\n</designspace>\n"
+        );
+        assert!(check_ufo(&ds_path, &fmt_options).unwrap().changed.is_empty());
+        let summary = format_ufo_clean(&ds_path, &None, &None, &None, &fmt_options).unwrap();
+        assert!(summary.changed.is_empty());
+        assert!(summary.unwritten.is_empty());
+
+        let res = format_file_contents(&ds_path, b"<designspace>", &fmt_options);
+        assert!(matches!(res, Err(Error::DesignspaceRead(_, _))));
+    }
+
+    #[test]
+    fn test_format_fea_not_formatted_by_default() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let copy_opt = CopyOptions::new();
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &copy_opt);
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        fs::write(test_ufo_path.join("features.fea"), "@a=[a  b];").unwrap();
+
+        let res_ufo_format =
+            format_ufo(&test_ufo_path, &None, &None, &None, &FormatOptions::default());
+        assert!(res_ufo_format.is_ok());
+
+        let test_fea_string = fs::read_to_string(test_ufo_path.join("features.fea")).unwrap();
+        assert_eq!(test_fea_string, "@a=[a  b];");
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Single file contents formatting
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+    #[test]
+    fn test_format_file_contents_matches_ufo_format() {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let expected_ufo_path = Path::new("testdata/expected/MutatorSansBoldCondensed-default.ufo");
+        for relpath in &[
+            "glyphs/A_.glif",
+            "glyphs/contents.plist",
+            "glyphs.background/layerinfo.plist",
+            "fontinfo.plist",
+            "groups.plist",
+            "kerning.plist",
+            "layercontents.plist",
+            "lib.plist",
+            "metainfo.plist",
+            "features.fea",
+        ] {
+            let contents = fs::read(src_ufo_path.join(relpath)).unwrap();
+            let res =
+                format_file_contents(Path::new(relpath), &contents, &FormatOptions::default());
+            assert!(res.is_ok(), "{}: {:?}", relpath, res);
+            let expected = fs::read_to_string(expected_ufo_path.join(relpath)).unwrap();
+            assert_eq!(String::from_utf8(res.unwrap()).unwrap(), expected, "{}", relpath);
+        }
+    }
+
+    #[test]
+    fn test_format_file_contents_contents_plist_custom_format() {
+        let contents = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<plist version=\"1.0\"><dict><key>b</key><string>b.glif</string><key>a</key><string>a.glif</string></dict></plist>";
+        let res = format_file_contents(
+            Path::new("glyphs/contents.plist"),
+            contents.as_bytes(),
+            &FormatOptions {
+                singlequotes: true,
+                indent_with_space: true,
+                indent_number: 2,
+                ..Default::default()
+            },
+        );
+        let expected = "<?xml version='1.0' encoding='UTF-8'?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+  <key>a</key>
+  <string>a.glif</string>
+  <key>b</key>
+  <string>b.glif</string>
+</dict>
+</plist>";
+        assert_eq!(String::from_utf8(res.unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_file_contents_features() {
+        let contents = b"feature liga {\r\nsub f i by f_i;\r\n} liga;\r\n";
+        let res =
+            format_file_contents(Path::new("features.fea"), contents, &FormatOptions::default());
+        assert_eq!(res.unwrap(), b"feature liga {\nsub f i by f_i;\n} liga;\n".to_vec());
+        let res = format_file_contents(
+            Path::new("features.fea"),
+            contents,
+            &FormatOptions { format_features: true, ..Default::default() },
+        );
+        assert_eq!(res.unwrap(), b"feature liga {\n\tsub f i by f_i;\n} liga;\n".to_vec());
+    }
+
+    #[test]
+    fn test_format_file_contents_unsupported_file_type() {
+        let res = format_file_contents(Path::new("images/A.png"), b"", &FormatOptions::default());
+        assert!(matches!(res, Err(Error::UnsupportedFileType(_))));
+    }
+
+    #[test]
+    fn test_format_file_contents_invalid_glif() {
+        let res = format_file_contents(Path::new("A_.glif"), b"<bad", &FormatOptions::default());
+        assert!(matches!(res, Err(Error::NoradRead(_, _))));
+    }
 }
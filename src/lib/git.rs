@@ -0,0 +1,154 @@
+//! git revision change detection
+//!
+//! The files that changed relative to a git revision are listed with the git
+//! executable.  Changed files include the committed, staged, and unstaged
+//! changes since the revision and the untracked files that are not ignored.
+//! The files are mapped to the UFO sources that contain them.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::lib::discovery;
+use crate::lib::errors::{Error, Result};
+use crate::lib::utils;
+
+/// Returns the canonical paths of the UFO sources with files that changed
+/// relative to `rev` in the git repository that contains `dir`
+pub(crate) fn get_changed_ufos(dir: &Path, rev: &str) -> Result<BTreeSet<PathBuf>> {
+    // revisions that start with '-' would be read as git options
+    if rev.starts_with('-') {
+        return Err(Error::InvalidRevision(rev.to_string()));
+    }
+    let toplevel = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(String::from_utf8_lossy(&toplevel).trim_end());
+    let commit_rev = format!("{}^{{commit}}", rev);
+    let commit = run_git(dir, &["rev-parse", "--verify", &commit_rev])?;
+    let commit = String::from_utf8_lossy(&commit).trim_end().to_string();
+    // the revision is separated from paths so that it is not read as a path
+    let diff = run_git(&root, &["diff", "--name-only", "--no-renames", "-z", &commit, "--"])?;
+    // untracked files are only listed in the working directory, so they are
+    // listed from the repository root
+    let untracked =
+        run_git(&root, &["ls-files", "--others", "--exclude-standard", "--full-name", "-z"])?;
+
+    let mut ufopaths = BTreeSet::new();
+    for contents in &[diff, untracked] {
+        for relpath in utils::parse_path_list(contents, true, Path::new("git"))? {
+            if let Some(ufopath) = get_owning_ufo(&root, &relpath) {
+                ufopaths.insert(ufopath);
+            }
+        }
+    }
+    Ok(ufopaths)
+}
+
+/// Returns the canonical path of the innermost existing UFO source that
+/// contains the file at `relpath` relative to the repository `root`
+fn get_owning_ufo(root: &Path, relpath: &Path) -> Option<PathBuf> {
+    relpath
+        .ancestors()
+        .skip(1)
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .map(|ancestor| root.join(ancestor))
+        .find(|path| path.is_dir() && discovery::is_ufo_dir(path))
+        .and_then(|path| path.canonicalize().ok())
+}
+
+/// Run git in `dir` and return stdout
+fn run_git(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let cmd = format!("git {}", args.join(" "));
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| Error::Git(cmd.clone(), e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Error::Git(cmd, stderr));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use fs_extra::dir::{copy, CopyOptions};
+    use tempdir::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let args = [&["-c", "user.name=test", "-c", "user.email=test@example.com"], args].concat();
+        assert!(run_git(dir, &args).is_ok());
+    }
+
+    /// Returns a git repository with two committed UFO sources
+    fn get_test_repo(tmp_dir: &TempDir) -> PathBuf {
+        let repo_path = tmp_dir.path().join("repo");
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        for dir in &["masters/a", "masters/b"] {
+            let dir_path = repo_path.join(dir);
+            fs::create_dir_all(&dir_path).unwrap();
+            assert!(copy(src_ufo_path, &dir_path, &CopyOptions::new()).is_ok());
+        }
+        fs::write(repo_path.join("README.md"), "").unwrap();
+        git(&repo_path, &["init", "-q"]);
+        git(&repo_path, &["add", "-A"]);
+        git(&repo_path, &["commit", "-q", "-m", "initial"]);
+        repo_path.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_get_changed_ufos() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let repo_path = get_test_repo(&tmp_dir);
+        let ufo_a = repo_path.join("masters/a/MutatorSansBoldCondensed.ufo");
+        let ufo_b = repo_path.join("masters/b/MutatorSansBoldCondensed.ufo");
+        assert!(get_changed_ufos(&repo_path, "HEAD").unwrap().is_empty());
+
+        // changes outside of UFO sources are ignored
+        fs::write(repo_path.join("README.md"), "changed").unwrap();
+        assert!(get_changed_ufos(&repo_path, "HEAD").unwrap().is_empty());
+
+        // unstaged changes and untracked files, from a subdirectory
+        fs::write(ufo_a.join("glyphs/A_.glif"), "").unwrap();
+        let expected: BTreeSet<PathBuf> = vec![ufo_a.clone()].into_iter().collect();
+        assert_eq!(get_changed_ufos(&repo_path.join("masters/b"), "HEAD").unwrap(), expected);
+        fs::write(ufo_b.join("glyphs/new.glif"), "").unwrap();
+        let expected: BTreeSet<PathBuf> = vec![ufo_a, ufo_b.clone()].into_iter().collect();
+        assert_eq!(get_changed_ufos(&repo_path, "HEAD").unwrap(), expected);
+        // untracked files outside of the working directory
+        assert_eq!(get_changed_ufos(&repo_path.join("masters/a"), "HEAD").unwrap(), expected);
+
+        // committed changes and deleted files
+        git(&repo_path, &["add", "-A"]);
+        git(&repo_path, &["commit", "-q", "-m", "changes"]);
+        assert!(get_changed_ufos(&repo_path, "HEAD").unwrap().is_empty());
+        assert_eq!(get_changed_ufos(&repo_path, "HEAD~1").unwrap().len(), 2);
+        fs::remove_file(ufo_b.join("glyphs/B_.glif")).unwrap();
+        let expected: BTreeSet<PathBuf> = vec![ufo_b].into_iter().collect();
+        assert_eq!(get_changed_ufos(&repo_path, "HEAD").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_get_changed_ufos_invalid() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let repo_path = get_test_repo(&tmp_dir);
+        let res = get_changed_ufos(&repo_path, "bogus-revision");
+        assert!(
+            matches!(res, Err(Error::Git(cmd, _)) if cmd.starts_with("git rev-parse --verify"))
+        );
+        // revisions are not read as git options
+        let output_path = tmp_dir.path().join("output.txt");
+        let rev = format!("--output={}", output_path.display());
+        let res = get_changed_ufos(&repo_path, &rev);
+        assert!(matches!(res, Err(Error::InvalidRevision(r)) if r == rev));
+        assert!(!output_path.exists());
+        let not_repo_path = tmp_dir.path().join("not-a-repo");
+        fs::create_dir(&not_repo_path).unwrap();
+        let res = get_changed_ufos(&not_repo_path, "HEAD");
+        assert!(matches!(res, Err(Error::Git(cmd, _)) if cmd == "git rev-parse --show-toplevel"));
+    }
+}
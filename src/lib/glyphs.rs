@@ -0,0 +1,788 @@
+//! Glyph level outline normalizations and identifier handling
+//!
+//! The normalizations do not change the rendered outline.  Points with a name,
+//! an identifier, or a lib are never removed.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use norad::{
+    Anchor, Component, Contour, ContourPoint, Glyph, Guideline, Identifier, Line, PointType,
+};
+
+use crate::lib::formatters::FormatOptions;
+use crate::lib::utils;
+
+/// Glyph object identifier handling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum IdentifierMode {
+    /// Write identifiers without changes
+    Keep,
+    /// Generate deterministic identifiers for objects without an identifier
+    Add,
+    /// Remove identifiers that are not referenced
+    Strip,
+}
+
+impl FromStr for IdentifierMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(IdentifierMode::Keep),
+            "add" => Ok(IdentifierMode::Add),
+            "strip" => Ok(IdentifierMode::Strip),
+            _ => Err(format!("unsupported identifier mode: {}", s)),
+        }
+    }
+}
+
+/// Glyph normalizations requested with the formatting options
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GlyphNormalizations {
+    pub(crate) point_types: bool,
+    pub(crate) duplicate_points: bool,
+    pub(crate) start_points: bool,
+    pub(crate) element_order: bool,
+    pub(crate) identifiers: IdentifierMode,
+}
+
+impl Default for GlyphNormalizations {
+    fn default() -> Self {
+        GlyphNormalizations {
+            point_types: false,
+            duplicate_points: false,
+            start_points: false,
+            element_order: false,
+            identifiers: IdentifierMode::Keep,
+        }
+    }
+}
+
+impl GlyphNormalizations {
+    pub(crate) fn from_options(fmt_options: &FormatOptions) -> Self {
+        GlyphNormalizations {
+            point_types: fmt_options.normalize_point_types,
+            duplicate_points: fmt_options.remove_duplicate_points,
+            start_points: fmt_options.normalize_start_points,
+            element_order: fmt_options.sort_elements,
+            identifiers: fmt_options.identifiers,
+        }
+    }
+
+    /// Returns true when no glyph normalization is requested
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == GlyphNormalizations::default()
+    }
+
+    /// Normalize the contours, the element order, and the identifiers of a glyph
+    pub(crate) fn apply(&self, glyph: &mut Glyph) {
+        for contour in glyph.contours.iter_mut() {
+            if self.point_types {
+                normalize_point_types(contour);
+            }
+            if self.duplicate_points {
+                remove_duplicate_points(contour);
+            }
+            if self.start_points {
+                normalize_start_point(contour);
+            }
+        }
+        if self.element_order {
+            sort_codepoints(glyph);
+            sort_anchors(&mut glyph.anchors);
+            sort_guidelines(&mut glyph.guidelines);
+        }
+        // identifiers are derived from the normalized object positions
+        match self.identifiers {
+            IdentifierMode::Keep => (),
+            IdentifierMode::Add => add_identifiers(glyph),
+            IdentifierMode::Strip => strip_identifiers(glyph),
+        }
+    }
+}
+
+fn is_closed(contour: &Contour) -> bool {
+    contour.points.first().is_some_and(|p| p.typ != PointType::Move)
+}
+
+fn is_on_curve(point: &ContourPoint) -> bool {
+    point.typ != PointType::OffCurve
+}
+
+/// Returns the index of the previous point in the contour, if any
+fn previous_index(contour: &Contour, i: usize) -> Option<usize> {
+    match (i, is_closed(contour)) {
+        (0, true) => Some(contour.points.len() - 1),
+        (0, false) => None,
+        _ => Some(i - 1),
+    }
+}
+
+/// Set the type of curve and qcurve points without offcurve points, which
+/// draw straight lines, to line
+pub(crate) fn normalize_point_types(contour: &mut Contour) {
+    if contour.points.len() < 2 {
+        return;
+    }
+    for i in 0..contour.points.len() {
+        if !matches!(contour.points[i].typ, PointType::Curve | PointType::QCurve) {
+            continue;
+        }
+        if let Some(prev) = previous_index(contour, i) {
+            if is_on_curve(&contour.points[prev]) {
+                contour.points[i].typ = PointType::Line;
+            }
+        }
+    }
+}
+
+/// Remove on-curve points that are at the same position as the previous
+/// on-curve point, which draw zero length straight lines
+pub(crate) fn remove_duplicate_points(contour: &mut Contour) {
+    let is_removable = |contour: &Contour, i: usize| {
+        let point = &contour.points[i];
+        match previous_index(contour, i) {
+            Some(prev) => {
+                let prev_point = &contour.points[prev];
+                is_on_curve(prev_point)
+                    && matches!(point.typ, PointType::Line | PointType::Curve | PointType::QCurve)
+                    && (point.x, point.y) == (prev_point.x, prev_point.y)
+                    && point.name.is_none()
+                    && point.identifier().is_none()
+                    && point.lib().is_none()
+            }
+            None => false,
+        }
+    };
+    while contour.points.len() > 2 {
+        match (0..contour.points.len()).find(|i| is_removable(contour, *i)) {
+            Some(i) => {
+                contour.points.remove(i);
+            }
+            None => break,
+        }
+    }
+}
+
+/// Rotate closed contours so that the on-curve point with the lowest y and
+/// then the lowest x coordinate is the start point.  Ties between points at
+/// the same position are broken by the point sequences that start with them,
+/// so rotations of the same contour have the same start point.
+pub(crate) fn normalize_start_point(contour: &mut Contour) {
+    if !is_closed(contour) {
+        return;
+    }
+    let points = &contour.points;
+    let rotated = |i: usize| points[i..].iter().chain(points[..i].iter());
+    // the point sequences start with the compared points
+    let start = (0..points.len()).filter(|i| is_on_curve(&points[*i])).min_by(|a, b| {
+        rotated(*a)
+            .zip(rotated(*b))
+            .map(|(p, q)| cmp_points(p, q))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    // contours with offcurve points only do not have a start point
+    if let Some(i) = start {
+        contour.points.rotate_left(i);
+    }
+}
+
+/// Compare points by position, type, smoothness, name, and identifier
+fn cmp_points(a: &ContourPoint, b: &ContourPoint) -> Ordering {
+    let type_rank = |p: &ContourPoint| match p.typ {
+        PointType::Move => 0,
+        PointType::Line => 1,
+        PointType::OffCurve => 2,
+        PointType::Curve => 3,
+        PointType::QCurve => 4,
+    };
+    cmp_f64s(&[a.y, a.x], &[b.y, b.x])
+        .then_with(|| type_rank(a).cmp(&type_rank(b)))
+        .then_with(|| a.smooth.cmp(&b.smooth))
+        .then_with(|| a.name.cmp(&b.name))
+        .then_with(|| {
+            a.identifier().map(|id| id.as_str()).cmp(&b.identifier().map(|id| id.as_str()))
+        })
+}
+
+/// Sort the glyph unicode values in ascending order after the primary unicode
+/// value, which is the first value
+pub(crate) fn sort_codepoints(glyph: &mut Glyph) {
+    if let Some((_, others)) = glyph.codepoints.split_first_mut() {
+        others.sort_unstable();
+    }
+}
+
+/// Sort anchors by name, and anchors with the same name by position.  Anchors
+/// without a name are sorted after the named anchors.
+pub(crate) fn sort_anchors(anchors: &mut [Anchor]) {
+    anchors.sort_by(|a, b| {
+        (a.name.is_none(), &a.name)
+            .cmp(&(b.name.is_none(), &b.name))
+            .then_with(|| cmp_f64s(&[a.x, a.y], &[b.x, b.y]))
+    });
+}
+
+/// Sort guidelines by position: vertical guidelines by x, then horizontal
+/// guidelines by y, then angled guidelines by x, y, and angle.  Guidelines at
+/// the same position are sorted by name.
+pub(crate) fn sort_guidelines(guidelines: &mut [Guideline]) {
+    let get_position = |guideline: &Guideline| match guideline.line {
+        Line::Vertical(x) => (0, [x, 0.0, 0.0]),
+        Line::Horizontal(y) => (1, [y, 0.0, 0.0]),
+        Line::Angle { x, y, degrees } => (2, [x, y, degrees]),
+    };
+    guidelines.sort_by(|a, b| {
+        let ((a_kind, a_coords), (b_kind, b_coords)) = (get_position(a), get_position(b));
+        a_kind
+            .cmp(&b_kind)
+            .then_with(|| cmp_f64s(&a_coords, &b_coords))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+fn cmp_f64s(a: &[f64], b: &[f64]) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+/// Add identifiers to the points, contours, components, anchors, and guidelines
+/// of a glyph that do not have one.  Identifiers are derived from the glyph name
+/// and the object position in the glyph, so they are stable across runs and
+/// glyphs with compatible outlines have the same identifiers.
+pub(crate) fn add_identifiers(glyph: &mut Glyph) {
+    let mut used = get_identifiers(glyph);
+    let name = glyph.name.to_string();
+    let mut new_id = |position: String| {
+        let mut seed = format!("{}:{}", name, position);
+        loop {
+            let id = format!("{:016x}", utils::fnv1a(seed.as_bytes()));
+            if used.insert(id.clone()) {
+                return Identifier::new(id).expect("hex identifiers are valid");
+            }
+            seed.push('+');
+        }
+    };
+    for (i, contour) in glyph.contours.iter_mut().enumerate() {
+        if contour.identifier().is_none() {
+            contour.replace_identifier(new_id(format!("contour{}", i)));
+        }
+        for (j, point) in contour.points.iter_mut().enumerate() {
+            if point.identifier().is_none() {
+                point.replace_identifier(new_id(format!("contour{}:point{}", i, j)));
+            }
+        }
+    }
+    for (i, component) in glyph.components.iter_mut().enumerate() {
+        if component.identifier().is_none() {
+            component.replace_identifier(new_id(format!("component{}", i)));
+        }
+    }
+    for (i, anchor) in glyph.anchors.iter_mut().enumerate() {
+        if anchor.identifier().is_none() {
+            anchor.replace_identifier(new_id(format!("anchor{}", i)));
+        }
+    }
+    for (i, guideline) in glyph.guidelines.iter_mut().enumerate() {
+        if guideline.identifier().is_none() {
+            guideline.replace_identifier(new_id(format!("guideline{}", i)));
+        }
+    }
+}
+
+/// Remove the identifiers of glyph objects that do not have an object lib and
+/// that are not referenced by a glyph lib key or string value
+pub(crate) fn strip_identifiers(glyph: &mut Glyph) {
+    let mut referenced = HashSet::new();
+    for (key, value) in glyph.lib.iter() {
+        referenced.insert(key.clone());
+        collect_strings(value, &mut referenced);
+    }
+    let is_removable = |id: Option<&Identifier>, has_lib: bool| match id {
+        Some(id) => !has_lib && !referenced.contains(id.as_str()),
+        None => false,
+    };
+    for contour in glyph.contours.iter_mut() {
+        for point in contour.points.iter_mut() {
+            if is_removable(point.identifier(), point.lib().is_some()) {
+                let name = point.name.take();
+                *point = ContourPoint::new(
+                    point.x,
+                    point.y,
+                    point.typ.clone(),
+                    point.smooth,
+                    name,
+                    None,
+                    None,
+                );
+            }
+        }
+        if is_removable(contour.identifier(), contour.lib().is_some()) {
+            *contour = Contour::new(std::mem::take(&mut contour.points), None, None);
+        }
+    }
+    for component in glyph.components.iter_mut() {
+        if is_removable(component.identifier(), component.lib().is_some()) {
+            *component = Component::new(component.base.clone(), component.transform, None, None);
+        }
+    }
+    for anchor in glyph.anchors.iter_mut() {
+        if is_removable(anchor.identifier(), anchor.lib().is_some()) {
+            *anchor = Anchor::new(
+                anchor.x,
+                anchor.y,
+                anchor.name.take(),
+                anchor.color.take(),
+                None,
+                None,
+            );
+        }
+    }
+    for guideline in glyph.guidelines.iter_mut() {
+        if is_removable(guideline.identifier(), guideline.lib().is_some()) {
+            *guideline = Guideline::new(
+                guideline.line.clone(),
+                guideline.name.take(),
+                guideline.color.take(),
+                None,
+                None,
+            );
+        }
+    }
+}
+
+/// Returns the identifiers of all objects in a glyph
+fn get_identifiers(glyph: &Glyph) -> HashSet<String> {
+    let contour_ids = glyph.contours.iter().flat_map(|contour| {
+        contour.points.iter().map(|p| p.identifier()).chain(std::iter::once(contour.identifier()))
+    });
+    contour_ids
+        .chain(glyph.components.iter().map(|c| c.identifier()))
+        .chain(glyph.anchors.iter().map(|a| a.identifier()))
+        .chain(glyph.guidelines.iter().map(|g| g.identifier()))
+        .flatten()
+        .map(|id| id.as_str().to_string())
+        .collect()
+}
+
+/// Collect the dictionary keys and the strings in a plist value
+fn collect_strings(value: &plist::Value, strings: &mut HashSet<String>) {
+    match value {
+        plist::Value::String(s) => {
+            strings.insert(s.clone());
+        }
+        plist::Value::Array(values) => values.iter().for_each(|v| collect_strings(v, strings)),
+        plist::Value::Dictionary(dict) => {
+            for (key, value) in dict.iter() {
+                strings.insert(key.clone());
+                collect_strings(value, strings);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use norad::{Font, Identifier};
+
+    /// A drawn contour segment: the start point, the offcurve points, the end point,
+    /// and whether the segment is a quadratic curve
+    type Segment = ((f64, f64), Vec<(f64, f64)>, (f64, f64), bool);
+
+    /// Returns the drawn segments of a contour without zero length straight lines.
+    /// Closed contour segments are rotated so that contours with the same outline
+    /// have the same segments.
+    fn get_segments(contour: &Contour) -> Vec<Segment> {
+        let points = &contour.points;
+        let on_curve: Vec<usize> = (0..points.len()).filter(|i| is_on_curve(&points[*i])).collect();
+        let mut segments = Vec::new();
+        for (n, &i) in on_curve.iter().enumerate() {
+            let prev = match (n, is_closed(contour)) {
+                (0, true) => *on_curve.last().unwrap(),
+                (0, false) => continue,
+                _ => on_curve[n - 1],
+            };
+            let mut offcurves = Vec::new();
+            let mut j = (prev + 1) % points.len();
+            while j != i {
+                offcurves.push((points[j].x, points[j].y));
+                j = (j + 1) % points.len();
+            }
+            let start = (points[prev].x, points[prev].y);
+            let end = (points[i].x, points[i].y);
+            if offcurves.is_empty() && start == end && on_curve.len() > 1 {
+                continue;
+            }
+            // segments without offcurve points are straight lines for all point types
+            let is_quadratic = !offcurves.is_empty() && points[i].typ == PointType::QCurve;
+            segments.push((start, offcurves, end, is_quadratic));
+        }
+        if is_closed(contour) && !segments.is_empty() {
+            let first = (0..segments.len())
+                .min_by(|a, b| format!("{:?}", segments[*a]).cmp(&format!("{:?}", segments[*b])))
+                .unwrap();
+            segments.rotate_left(first);
+        }
+        segments
+    }
+
+    fn point(x: f64, y: f64, typ: PointType) -> ContourPoint {
+        ContourPoint::new(x, y, typ, false, None, None, None)
+    }
+
+    fn get_types(contour: &Contour) -> Vec<PointType> {
+        contour.points.iter().map(|p| p.typ.clone()).collect()
+    }
+
+    #[test]
+    fn test_normalize_point_types() {
+        let mut contour = Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::Curve),
+                point(0.0, 100.0, PointType::Curve),
+                point(50.0, 150.0, PointType::OffCurve),
+                point(100.0, 100.0, PointType::QCurve),
+                point(100.0, 0.0, PointType::QCurve),
+                point(50.0, -50.0, PointType::OffCurve),
+            ],
+            None,
+            None,
+        );
+        let segments = get_segments(&contour);
+        normalize_point_types(&mut contour);
+        assert_eq!(
+            get_types(&contour),
+            vec![
+                PointType::Curve,
+                PointType::Line,
+                PointType::OffCurve,
+                PointType::QCurve,
+                PointType::Line,
+                PointType::OffCurve,
+            ]
+        );
+        assert_eq!(get_segments(&contour), segments);
+
+        // open contours start with a move point
+        let mut contour = Contour::new(
+            vec![point(0.0, 0.0, PointType::Move), point(0.0, 100.0, PointType::Curve)],
+            None,
+            None,
+        );
+        normalize_point_types(&mut contour);
+        assert_eq!(get_types(&contour), vec![PointType::Move, PointType::Line]);
+    }
+
+    #[test]
+    fn test_remove_duplicate_points() {
+        let named =
+            ContourPoint::new(0.0, 100.0, PointType::Line, false, Some("top".into()), None, None);
+        let mut contour = Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::Line),
+                point(0.0, 0.0, PointType::Line),
+                point(0.0, 100.0, PointType::Line),
+                named,
+                point(100.0, 100.0, PointType::Curve),
+                point(100.0, 100.0, PointType::OffCurve),
+                point(100.0, 100.0, PointType::OffCurve),
+                point(100.0, 100.0, PointType::Curve),
+                point(100.0, 0.0, PointType::Line),
+                point(0.0, 0.0, PointType::Line),
+            ],
+            None,
+            None,
+        );
+        let segments = get_segments(&contour);
+        remove_duplicate_points(&mut contour);
+        let coords: Vec<(f64, f64)> = contour.points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(
+            coords,
+            vec![
+                (0.0, 100.0),
+                (0.0, 100.0),
+                (100.0, 100.0),
+                (100.0, 100.0),
+                (100.0, 100.0),
+                (100.0, 100.0),
+                (100.0, 0.0),
+                (0.0, 0.0),
+            ]
+        );
+        // the named point is kept
+        assert_eq!(contour.points[1].name, Some("top".to_string()));
+        assert_eq!(get_segments(&contour), segments);
+    }
+
+    #[test]
+    fn test_remove_duplicate_points_keeps_attributes_and_open_contours() {
+        let with_id = ContourPoint::new(
+            0.0,
+            0.0,
+            PointType::Line,
+            false,
+            None,
+            Some(Identifier::new("abc").unwrap()),
+            None,
+        );
+        let mut contour = Contour::new(
+            vec![point(0.0, 0.0, PointType::Move), with_id, point(0.0, 100.0, PointType::Line)],
+            None,
+            None,
+        );
+        remove_duplicate_points(&mut contour);
+        assert_eq!(contour.points.len(), 3);
+
+        // open contours do not wrap around
+        let mut contour = Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::Move),
+                point(0.0, 100.0, PointType::Line),
+                point(0.0, 0.0, PointType::Line),
+            ],
+            None,
+            None,
+        );
+        remove_duplicate_points(&mut contour);
+        assert_eq!(contour.points.len(), 3);
+
+        // contours are not reduced to fewer than 2 points
+        let mut contour = Contour::new(
+            vec![point(0.0, 0.0, PointType::Line), point(0.0, 0.0, PointType::Line)],
+            None,
+            None,
+        );
+        remove_duplicate_points(&mut contour);
+        assert_eq!(contour.points.len(), 2);
+    }
+
+    #[test]
+    fn test_normalize_start_point() {
+        let mut contour = Contour::new(
+            vec![
+                point(100.0, 100.0, PointType::Line),
+                point(100.0, 50.0, PointType::OffCurve),
+                point(50.0, 0.0, PointType::OffCurve),
+                point(0.0, 0.0, PointType::Curve),
+                point(0.0, 100.0, PointType::Line),
+            ],
+            None,
+            None,
+        );
+        let segments = get_segments(&contour);
+        normalize_start_point(&mut contour);
+        let coords: Vec<(f64, f64)> = contour.points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(
+            coords,
+            vec![(0.0, 0.0), (0.0, 100.0), (100.0, 100.0), (100.0, 50.0), (50.0, 0.0)]
+        );
+        assert_eq!(get_segments(&contour), segments);
+
+        // rotations of the same contour have the same start point
+        let mut rotated = contour.clone();
+        rotated.points.rotate_left(2);
+        normalize_start_point(&mut rotated);
+        assert_eq!(rotated, contour);
+
+        // points at the same position are ordered by the point sequences that start with them
+        let tied_points = vec![
+            point(0.0, 0.0, PointType::Line),
+            point(100.0, 0.0, PointType::Line),
+            point(0.0, 0.0, PointType::Line),
+            point(0.0, 100.0, PointType::Line),
+        ];
+        let mut tied = Contour::new(tied_points.clone(), None, None);
+        let mut tied_rotated = Contour::new(tied_points, None, None);
+        tied_rotated.points.rotate_left(2);
+        normalize_start_point(&mut tied);
+        normalize_start_point(&mut tied_rotated);
+        assert_eq!(tied_rotated, tied);
+        let coords: Vec<(f64, f64)> = tied.points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(coords, vec![(0.0, 0.0), (100.0, 0.0), (0.0, 0.0), (0.0, 100.0)]);
+
+        // open contours and offcurve only contours are not modified
+        let open_points =
+            vec![point(100.0, 100.0, PointType::Move), point(0.0, 0.0, PointType::Line)];
+        let mut open = Contour::new(open_points.clone(), None, None);
+        normalize_start_point(&mut open);
+        assert_eq!(open.points, open_points);
+        let offcurve_points =
+            vec![point(100.0, 100.0, PointType::OffCurve), point(0.0, 0.0, PointType::OffCurve)];
+        let mut offcurve_only = Contour::new(offcurve_points.clone(), None, None);
+        normalize_start_point(&mut offcurve_only);
+        assert_eq!(offcurve_only.points, offcurve_points);
+    }
+
+    fn get_test_glyph() -> Glyph {
+        let mut glyph = Glyph::new_named("A");
+        glyph.contours.push(Contour::new(
+            vec![point(0.0, 0.0, PointType::Line), point(0.0, 100.0, PointType::Line)],
+            None,
+            None,
+        ));
+        glyph.anchors.push(Anchor::new(50.0, 100.0, Some("top".into()), None, None, None));
+        glyph.guidelines.push(Guideline::new(
+            Line::Horizontal(100.0),
+            None,
+            None,
+            Some(Identifier::new("guide").unwrap()),
+            None,
+        ));
+        glyph
+    }
+
+    #[test]
+    fn test_identifier_mode_from_str() {
+        assert_eq!("keep".parse::<IdentifierMode>(), Ok(IdentifierMode::Keep));
+        assert_eq!("add".parse::<IdentifierMode>(), Ok(IdentifierMode::Add));
+        assert_eq!("strip".parse::<IdentifierMode>(), Ok(IdentifierMode::Strip));
+        assert!("remove".parse::<IdentifierMode>().is_err());
+    }
+
+    #[test]
+    fn test_add_identifiers() {
+        let mut glyph = get_test_glyph();
+        add_identifiers(&mut glyph);
+        let ids = get_identifiers(&glyph);
+        // 2 points, 1 contour, 1 anchor, 1 guideline
+        assert_eq!(ids.len(), 5);
+        // existing identifiers are kept
+        assert_eq!(glyph.guidelines[0].identifier().unwrap().as_str(), "guide");
+
+        // identifiers are stable across runs
+        let mut other = get_test_glyph();
+        add_identifiers(&mut other);
+        assert_eq!(other, glyph);
+        add_identifiers(&mut other);
+        assert_eq!(other, glyph);
+
+        // identifiers depend on the glyph name
+        let mut renamed = get_test_glyph();
+        renamed.name = "B".into();
+        add_identifiers(&mut renamed);
+        assert!(get_identifiers(&renamed).intersection(&ids).all(|id| id == "guide"));
+    }
+
+    #[test]
+    fn test_add_identifiers_avoids_existing_identifiers() {
+        let mut glyph = get_test_glyph();
+        let anchor_id = format!("{:016x}", utils::fnv1a(b"A:anchor0"));
+        glyph.guidelines[0].replace_identifier(Identifier::new(anchor_id.clone()).unwrap());
+        add_identifiers(&mut glyph);
+        assert_eq!(get_identifiers(&glyph).len(), 5);
+        assert_ne!(glyph.anchors[0].identifier().unwrap().as_str(), anchor_id);
+    }
+
+    #[test]
+    fn test_strip_identifiers() {
+        let mut glyph = get_test_glyph();
+        add_identifiers(&mut glyph);
+        // a point with an object lib, and a contour identifier referenced by the glyph lib
+        let mut lib = plist::Dictionary::new();
+        lib.insert("com.example.key".into(), "value".into());
+        glyph.contours[0].points[1].replace_lib(lib);
+        let contour_id = glyph.contours[0].identifier().unwrap().as_str().to_string();
+        glyph.lib.insert(
+            "com.example.contours".into(),
+            plist::Value::Array(vec![contour_id.clone().into()]),
+        );
+        let point_id = glyph.contours[0].points[1].identifier().unwrap().clone();
+
+        strip_identifiers(&mut glyph);
+        let ids = get_identifiers(&glyph);
+        assert_eq!(ids, vec![contour_id, point_id.as_str().to_string()].into_iter().collect());
+        assert!(glyph.contours[0].points[1].lib().is_some());
+        assert_eq!(glyph.anchors[0].name, Some("top".to_string()));
+        assert_eq!(glyph.guidelines[0].line, Line::Horizontal(100.0));
+    }
+
+    #[test]
+    fn test_sort_codepoints() {
+        let mut glyph = Glyph::new_named("A");
+        glyph.codepoints = vec!['b', 'c', 'a', 'B'];
+        sort_codepoints(&mut glyph);
+        assert_eq!(glyph.codepoints, vec!['b', 'B', 'a', 'c']);
+        glyph.codepoints.clear();
+        sort_codepoints(&mut glyph);
+        assert!(glyph.codepoints.is_empty());
+    }
+
+    #[test]
+    fn test_sort_anchors() {
+        let anchor = |name: Option<&str>, x: f64| {
+            Anchor::new(x, 0.0, name.map(String::from), None, None, None)
+        };
+        let mut anchors = vec![
+            anchor(None, 10.0),
+            anchor(Some("top"), 20.0),
+            anchor(Some("bottom"), 0.0),
+            anchor(Some("top"), 10.0),
+            anchor(None, 0.0),
+        ];
+        sort_anchors(&mut anchors);
+        let sorted: Vec<(Option<&str>, f64)> =
+            anchors.iter().map(|a| (a.name.as_deref(), a.x)).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                (Some("bottom"), 0.0),
+                (Some("top"), 10.0),
+                (Some("top"), 20.0),
+                (None, 0.0),
+                (None, 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_guidelines() {
+        let guideline = |line: Line| Guideline::new(line, None, None, None, None);
+        let mut guidelines = vec![
+            guideline(Line::Angle { x: 0.0, y: 10.0, degrees: 45.0 }),
+            guideline(Line::Horizontal(500.0)),
+            guideline(Line::Vertical(20.0)),
+            guideline(Line::Angle { x: 0.0, y: 10.0, degrees: 30.0 }),
+            guideline(Line::Horizontal(-10.0)),
+            guideline(Line::Vertical(10.0)),
+        ];
+        sort_guidelines(&mut guidelines);
+        let lines: Vec<Line> = guidelines.into_iter().map(|g| g.line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                Line::Vertical(10.0),
+                Line::Vertical(20.0),
+                Line::Horizontal(-10.0),
+                Line::Horizontal(500.0),
+                Line::Angle { x: 0.0, y: 10.0, degrees: 30.0 },
+                Line::Angle { x: 0.0, y: 10.0, degrees: 45.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalizations_do_not_change_outlines() {
+        let ufo = Font::load(Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo")).unwrap();
+        let mut contour_count = 0;
+        for layer in ufo.layers.iter() {
+            for glyph in layer.iter() {
+                for contour in glyph.contours.iter() {
+                    let mut normalized = contour.clone();
+                    normalize_point_types(&mut normalized);
+                    remove_duplicate_points(&mut normalized);
+                    normalize_start_point(&mut normalized);
+                    assert_eq!(get_segments(&normalized), get_segments(contour));
+                    contour_count += 1;
+                }
+            }
+        }
+        assert!(contour_count > 0);
+    }
+}
@@ -0,0 +1,228 @@
+//! Lenient UFO source reads
+//!
+//! norad fails to load a UFO source when any glif file cannot be parsed.  In
+//! lenient mode the glif files are parsed one at a time first.  The UFO source
+//! is loaded from a staging copy without the unparsable glif files, and the
+//! unparsable glif files are written to the formatted UFO source without changes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use norad::{Font, Glyph, WriteOptions};
+use rayon::prelude::*;
+use tempdir::TempDir;
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::formatters;
+use crate::lib::utils;
+
+/// glif file that is skipped because it cannot be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SkippedGlif {
+    /// source glif file path
+    pub(crate) path: PathBuf,
+    /// layer directory path relative to the UFO source
+    pub(crate) layer_dir: PathBuf,
+    pub(crate) glyph_name: String,
+    pub(crate) file_name: String,
+    /// parse error message
+    pub(crate) error: String,
+    /// unmodified glif file contents
+    pub(crate) contents: Vec<u8>,
+}
+
+/// UFO source that is loaded without the unparsable glif files
+pub(crate) struct LenientUfo {
+    pub(crate) ufo: Font,
+    pub(crate) skipped_glifs: Vec<SkippedGlif>,
+    /// staging copy that the UFO source data and images are read from
+    _staging_dir: Option<TempDir>,
+}
+
+/// Load a UFO source.  Unparsable glif files are skipped when `lenient` is true.
+pub(crate) fn load_ufo(ufopath: &Path, lenient: bool) -> Result<LenientUfo> {
+    let load = |path: &Path| Font::load(path).map_err(|e| Error::NoradRead(ufopath.into(), e));
+    let skipped_glifs = match lenient {
+        true => find_unparsable_glifs(ufopath),
+        false => Vec::new(),
+    };
+    if skipped_glifs.is_empty() {
+        return Ok(LenientUfo { ufo: load(ufopath)?, skipped_glifs, _staging_dir: None });
+    }
+
+    let staging_dir = TempDir::new("ufofmt").map_err(|e| Error::Io(std::env::temp_dir(), e))?;
+    let staged_path = staging_dir.path().join("lenient.ufo");
+    utils::copy_dir_all(ufopath, &staged_path).map_err(|e| Error::Io(ufopath.into(), e))?;
+    for (layer_dir, glifs) in group_by_layer(&skipped_glifs) {
+        let contents_path = staged_path.join(layer_dir).join("contents.plist");
+        let mut glyph_files = read_contents_plist(&contents_path)?;
+        for glif in glifs {
+            glyph_files.remove(&glif.glyph_name);
+            let glif_path = staged_path.join(layer_dir).join(&glif.file_name);
+            fs::remove_file(&glif_path).map_err(|e| Error::Io(glif_path, e))?;
+        }
+        plist::to_file_xml(&contents_path, &glyph_files)
+            .map_err(|e| Error::NoradWrite(contents_path.clone(), e.into()))?;
+    }
+    Ok(LenientUfo { ufo: load(&staged_path)?, skipped_glifs, _staging_dir: Some(staging_dir) })
+}
+
+/// Returns the glif files in all layers that cannot be parsed.  Layers with
+/// unreadable layercontents.plist or contents.plist files are not searched
+/// because the UFO source fails to load.
+pub(crate) fn find_unparsable_glifs(ufopath: &Path) -> Vec<SkippedGlif> {
+    let layercontents_path = ufopath.join("layercontents.plist");
+    let layer_dirs: Vec<(String, PathBuf)> = match layercontents_path.exists() {
+        true => plist::from_file(&layercontents_path).unwrap_or_default(),
+        false => vec![("public.default".to_string(), PathBuf::from("glyphs"))],
+    };
+    let mut glifs = Vec::new();
+    for (_, layer_dir) in layer_dirs {
+        let contents_path = ufopath.join(&layer_dir).join("contents.plist");
+        if let Ok(glyph_files) = read_contents_plist(&contents_path) {
+            glifs.extend(glyph_files.into_iter().map(|entry| (layer_dir.clone(), entry)));
+        }
+    }
+    glifs
+        .into_par_iter()
+        .filter_map(|(layer_dir, (glyph_name, file_name))| {
+            let path = ufopath.join(&layer_dir).join(&file_name);
+            // missing glif files fail to load
+            let contents = fs::read(&path).ok()?;
+            let error = Glyph::load(&path).err()?;
+            Some(SkippedGlif {
+                path,
+                layer_dir,
+                glyph_name,
+                file_name,
+                error: error.to_string(),
+                contents,
+            })
+        })
+        .collect()
+}
+
+/// Write the skipped glif files without changes to the UFO source at `outpath`
+/// and add them to the layer contents.plist files
+pub(crate) fn restore_glifs(
+    skipped_glifs: &[SkippedGlif],
+    outpath: &Path,
+    options: &WriteOptions,
+    singlequotes: bool,
+) -> Result<()> {
+    for (layer_dir, glifs) in group_by_layer(skipped_glifs) {
+        let layer_path = outpath.join(layer_dir);
+        let contents_path = layer_path.join("contents.plist");
+        let mut glyph_files = read_contents_plist(&contents_path)?;
+        for glif in glifs {
+            let glif_path = layer_path.join(&glif.file_name);
+            if glif_path.exists() {
+                let msg = "a formatted glif file with the same name was written";
+                return Err(Error::Io(
+                    glif_path,
+                    io::Error::new(io::ErrorKind::AlreadyExists, msg),
+                ));
+            }
+            fs::write(&glif_path, &glif.contents).map_err(|e| Error::Io(glif_path, e))?;
+            glyph_files.insert(glif.glyph_name.clone(), glif.file_name.clone());
+        }
+        let contents =
+            formatters::encode_contents_plist(&contents_path, &glyph_files, options, singlequotes)?;
+        fs::write(&contents_path, contents).map_err(|e| Error::Io(contents_path.clone(), e))?;
+    }
+    Ok(())
+}
+
+fn group_by_layer(skipped_glifs: &[SkippedGlif]) -> BTreeMap<&Path, Vec<&SkippedGlif>> {
+    let mut layers: BTreeMap<&Path, Vec<&SkippedGlif>> = BTreeMap::new();
+    for glif in skipped_glifs {
+        layers.entry(glif.layer_dir.as_path()).or_default().push(glif);
+    }
+    layers
+}
+
+fn read_contents_plist(contents_path: &Path) -> Result<BTreeMap<String, String>> {
+    plist::from_file(contents_path).map_err(|e| Error::NoradRead(contents_path.into(), e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fs_extra::dir::{copy, CopyOptions};
+
+    fn get_test_ufo(tmp_dir: &TempDir) -> PathBuf {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        for glif in &["glyphs/B_.glif", "glyphs.background/S_.closed.glif"] {
+            fs::write(test_ufo_path.join(glif), "<glyph name=\"B\" format=\"2\"><outline>")
+                .unwrap();
+        }
+        test_ufo_path
+    }
+
+    #[test]
+    fn test_find_unparsable_glifs() {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        assert!(find_unparsable_glifs(src_ufo_path).is_empty());
+
+        let tmp_dir = TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        let mut skipped: Vec<(String, String)> = find_unparsable_glifs(&test_ufo_path)
+            .into_iter()
+            .map(|g| {
+                (g.glyph_name, g.path.strip_prefix(&test_ufo_path).unwrap().display().to_string())
+            })
+            .collect();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            vec![
+                ("B".to_string(), "glyphs/B_.glif".to_string()),
+                ("S.closed".to_string(), "glyphs.background/S_.closed.glif".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_ufo() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        assert!(matches!(load_ufo(&test_ufo_path, false), Err(Error::NoradRead(_, _))));
+
+        let loaded = load_ufo(&test_ufo_path, true).unwrap();
+        assert_eq!(loaded.skipped_glifs.len(), 2);
+        assert!(loaded.ufo.layers.default_layer().get_glyph("A").is_some());
+        assert!(loaded.ufo.layers.default_layer().get_glyph("B").is_none());
+        // the source is not modified
+        let contents_path = test_ufo_path.join("glyphs").join("contents.plist");
+        assert!(read_contents_plist(&contents_path).unwrap().contains_key("B"));
+    }
+
+    #[test]
+    fn test_restore_glifs() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        let loaded = load_ufo(&test_ufo_path, true).unwrap();
+        let out_path = tmp_dir.path().join("out.ufo");
+        loaded.ufo.save(&out_path).unwrap();
+        let options = WriteOptions::default();
+        assert!(restore_glifs(&loaded.skipped_glifs, &out_path, &options, false).is_ok());
+        for glif in &["glyphs/B_.glif", "glyphs.background/S_.closed.glif"] {
+            assert_eq!(
+                fs::read(out_path.join(glif)).unwrap(),
+                fs::read(test_ufo_path.join(glif)).unwrap()
+            );
+        }
+        let glyph_files = read_contents_plist(&out_path.join("glyphs/contents.plist")).unwrap();
+        assert_eq!(glyph_files.get("B"), Some(&"B_.glif".to_string()));
+
+        // restored glif files do not replace written glif files
+        let res = restore_glifs(&loaded.skipped_glifs, &out_path, &options, false);
+        assert!(matches!(res, Err(Error::Io(p, _)) if p == out_path.join("glyphs/B_.glif")));
+    }
+}
@@ -0,0 +1,345 @@
+//! Line endings of formatted glif, plist, and feature files
+//!
+//! norad writes LF line endings.  The line breaks that the XML serializers
+//! write between markup are converted to CRLF line endings, or to the dominant
+//! line ending of each source file in preserve mode.  Line breaks in text
+//! contents, e.g., in glif notes and plist strings, are data and are not
+//! converted.  All line breaks of feature files are converted.  Files in the
+//! data and images directories are not converted.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::utils::{self, UfoFileType};
+
+/// Line ending policy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LineEnding {
+    Lf,
+    Crlf,
+    /// dominant line ending of the source file
+    Preserve,
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            "preserve" => Ok(LineEnding::Preserve),
+            _ => Err(format!("unsupported line ending: {}", s)),
+        }
+    }
+}
+
+/// Line endings of the files in a UFO source by relative path
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UfoLineEndings {
+    by_path: BTreeMap<PathBuf, LineEnding>,
+    /// line ending of the files without a source file line ending
+    default: LineEnding,
+}
+
+impl UfoLineEndings {
+    /// Detect the source file line endings in preserve mode.  Files that are
+    /// not in the UFO source, e.g., renamed glif files, use the dominant line
+    /// ending of the UFO source files.
+    pub(crate) fn from_source(ufopath: &Path, line_ending: LineEnding) -> Result<Self> {
+        let mut by_path = BTreeMap::new();
+        if line_ending != LineEnding::Preserve {
+            return Ok(UfoLineEndings { by_path, default: line_ending });
+        }
+        let (mut lf_count, mut crlf_count) = (0, 0);
+        for relpath in list_text_files(ufopath)? {
+            let path = ufopath.join(&relpath);
+            let contents = fs::read(&path).map_err(|e| Error::Io(path, e))?;
+            if let Some(detected) = detect_line_ending(&contents) {
+                match detected {
+                    LineEnding::Crlf => crlf_count += 1,
+                    _ => lf_count += 1,
+                }
+                by_path.insert(relpath, detected);
+            }
+        }
+        let default = if crlf_count > lf_count { LineEnding::Crlf } else { LineEnding::Lf };
+        Ok(UfoLineEndings { by_path, default })
+    }
+
+    fn get(&self, relpath: &Path) -> LineEnding {
+        self.by_path.get(relpath).copied().unwrap_or(self.default)
+    }
+
+    /// Returns the features with the features.fea file line ending
+    pub(crate) fn convert_features(&self, features: &str) -> String {
+        convert_text_line_endings(features, self.get(Path::new("features.fea")))
+    }
+
+    /// Convert the line endings of the glif, plist, and feature files in the
+    /// UFO source at `outpath`.  Files in `unchanged` are not converted.  norad
+    /// writes LF line endings, so only the files with CRLF line endings are read.
+    pub(crate) fn apply(&self, outpath: &Path, unchanged: &[PathBuf]) -> Result<()> {
+        let has_crlf = self.default == LineEnding::Crlf
+            || self.by_path.values().any(|line_ending| *line_ending == LineEnding::Crlf);
+        if !has_crlf {
+            return Ok(());
+        }
+        for relpath in list_text_files(outpath)? {
+            let line_ending = self.get(&relpath);
+            if line_ending == LineEnding::Lf || unchanged.contains(&relpath) {
+                continue;
+            }
+            let filetype = match utils::get_ufo_file_type(&relpath) {
+                Some(filetype) => filetype,
+                None => continue,
+            };
+            let path = outpath.join(&relpath);
+            let contents = fs::read(&path).map_err(|e| Error::Io(path.clone(), e))?;
+            let converted = convert_file_line_endings(&contents, filetype, line_ending);
+            if converted != contents {
+                fs::write(&path, converted).map_err(|e| Error::Io(path, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the relative paths of the glif, plist, and feature files in a UFO
+/// source outside of the data and images directories
+fn list_text_files(ufopath: &Path) -> Result<Vec<PathBuf>> {
+    let files = utils::list_files(ufopath).map_err(|e| Error::Io(ufopath.into(), e))?;
+    Ok(files
+        .into_iter()
+        .filter(|p| !p.starts_with("data") && !p.starts_with("images"))
+        .filter(|p| utils::get_ufo_file_type(p).is_some())
+        .collect())
+}
+
+/// Returns the dominant line ending of file contents, or None for contents
+/// without line breaks.  Ties are LF.
+pub(crate) fn detect_line_ending(contents: &[u8]) -> Option<LineEnding> {
+    let lf_total = contents.iter().filter(|b| **b == b'\n').count();
+    let crlf_count = contents.windows(2).filter(|w| w == b"\r\n").count();
+    match lf_total {
+        0 => None,
+        _ if crlf_count > lf_total - crlf_count => Some(LineEnding::Crlf),
+        _ => Some(LineEnding::Lf),
+    }
+}
+
+/// Returns the line ending of formatted contents for the line ending policy.
+/// `source` is the unformatted file contents in preserve mode.
+pub(crate) fn resolve_line_ending(line_ending: LineEnding, source: &[u8]) -> LineEnding {
+    match line_ending {
+        LineEnding::Preserve => detect_line_ending(source).unwrap_or(LineEnding::Lf),
+        _ => line_ending,
+    }
+}
+
+/// Convert all CRLF and LF line breaks to `line_ending`.  Lone CR chars are not
+/// line breaks and are not changed.
+pub(crate) fn convert_line_endings(contents: &[u8], line_ending: LineEnding) -> Vec<u8> {
+    let mut converted = Vec::with_capacity(contents.len());
+    for (i, byte) in contents.iter().enumerate() {
+        match byte {
+            b'\r' if contents.get(i + 1) == Some(&b'\n') => continue,
+            b'\n' if line_ending == LineEnding::Crlf => converted.extend_from_slice(b"\r\n"),
+            _ => converted.push(*byte),
+        }
+    }
+    converted
+}
+
+/// Convert all line breaks of a text file, e.g., a feature file
+pub(crate) fn convert_text_line_endings(text: &str, line_ending: LineEnding) -> String {
+    // line break conversion keeps the UTF-8 contents valid
+    String::from_utf8(convert_line_endings(text.as_bytes(), line_ending))
+        .unwrap_or_else(|_| text.to_string())
+}
+
+/// Convert the line breaks of formatted UFO source file contents.  Feature
+/// files are text files, the other file types are XML files.
+pub(crate) fn convert_file_line_endings(
+    contents: &[u8],
+    filetype: UfoFileType,
+    line_ending: LineEnding,
+) -> Vec<u8> {
+    match filetype {
+        UfoFileType::Features => convert_line_endings(contents, line_ending),
+        _ => convert_xml_line_endings(contents, line_ending),
+    }
+}
+
+/// Convert the line breaks in the whitespace between XML markup to
+/// `line_ending`.  Line breaks in text contents, attribute values, comments,
+/// and CDATA sections are not changed.  Whitespace-only text that is the only
+/// contents of an element, e.g., in `<string>\n</string>`, is text contents.
+pub(crate) fn convert_xml_line_endings(contents: &[u8], line_ending: LineEnding) -> Vec<u8> {
+    let mut converted = Vec::with_capacity(contents.len());
+    let mut pos = 0;
+    let mut after_start_tag = false;
+    while pos < contents.len() {
+        if contents[pos] == b'<' {
+            let end = get_markup_end(contents, pos);
+            converted.extend_from_slice(&contents[pos..end]);
+            after_start_tag = is_start_tag(&contents[pos..end]);
+            pos = end;
+            continue;
+        }
+        let end =
+            contents[pos..].iter().position(|b| *b == b'<').map_or(contents.len(), |i| pos + i);
+        let text = &contents[pos..end];
+        let is_markup_whitespace = text.iter().all(u8::is_ascii_whitespace)
+            && !(after_start_tag && contents[end..].starts_with(b"</"));
+        match is_markup_whitespace {
+            true => converted.extend(convert_line_endings(text, line_ending)),
+            false => converted.extend_from_slice(text),
+        }
+        pos = end;
+    }
+    converted
+}
+
+/// Returns the end index of the markup that starts with `<` at `start`.
+/// Unterminated markup ends at the end of `contents`.
+fn get_markup_end(contents: &[u8], start: usize) -> usize {
+    let markup = &contents[start..];
+    let delimiters: [(&[u8], &[u8]); 3] =
+        [(b"<!--", b"-->"), (b"<![CDATA[", b"]]>"), (b"<?", b"?>")];
+    for (open, close) in delimiters.iter() {
+        if markup.starts_with(open) {
+            return markup[open.len()..]
+                .windows(close.len())
+                .position(|w| w == *close)
+                .map_or(contents.len(), |i| start + open.len() + i + close.len());
+        }
+    }
+    // tags and declarations end at the first '>' outside of quoted values
+    let mut quote = None;
+    for (i, byte) in markup.iter().enumerate() {
+        match (quote, *byte) {
+            (None, b'"') | (None, b'\'') => quote = Some(*byte),
+            (Some(q), b) if q == b => quote = None,
+            (None, b'>') => return start + i + 1,
+            _ => (),
+        }
+    }
+    contents.len()
+}
+
+/// Returns true for start tags, which are not end tags, empty element tags,
+/// comments, declarations, or processing instructions
+fn is_start_tag(markup: &[u8]) -> bool {
+    !matches!(markup.get(1), Some(b'/') | Some(b'!') | Some(b'?')) && !markup.ends_with(b"/>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fs_extra::dir::{copy, CopyOptions};
+
+    #[test]
+    fn test_line_ending_from_str() {
+        assert_eq!(LineEnding::from_str("lf"), Ok(LineEnding::Lf));
+        assert_eq!(LineEnding::from_str("crlf"), Ok(LineEnding::Crlf));
+        assert_eq!(LineEnding::from_str("preserve"), Ok(LineEnding::Preserve));
+        assert_eq!(LineEnding::from_str("cr"), Err("unsupported line ending: cr".to_string()));
+    }
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(detect_line_ending(b"a\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(detect_line_ending(b"a\r\nb\r\nc\n"), Some(LineEnding::Crlf));
+        assert_eq!(detect_line_ending(b"a\r\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(detect_line_ending(b"a\rb"), None);
+        assert_eq!(detect_line_ending(b""), None);
+        assert_eq!(resolve_line_ending(LineEnding::Preserve, b"a"), LineEnding::Lf);
+        assert_eq!(resolve_line_ending(LineEnding::Crlf, b"a\nb\n"), LineEnding::Crlf);
+    }
+
+    #[test]
+    fn test_convert_line_endings() {
+        assert_eq!(convert_line_endings(b"a\nb\r\nc\rd", LineEnding::Crlf), b"a\r\nb\r\nc\rd");
+        assert_eq!(convert_line_endings(b"a\nb\r\nc\rd", LineEnding::Lf), b"a\nb\nc\rd");
+    }
+
+    #[test]
+    fn test_convert_xml_line_endings() {
+        let source = "<?xml version=\"1.0\"?>\n<!DOCTYPE plist PUBLIC \"a>b\">\n<dict>\n\t<key>a\nb</key>\n\t<string>\n</string>\n\t<string a=\"1\n2\"/>\n\t<!-- c\nd -->\n\t<![CDATA[e\nf]]>\n</dict>\n";
+        let expected = "<?xml version=\"1.0\"?>\r\n<!DOCTYPE plist PUBLIC \"a>b\">\r\n<dict>\r\n\t<key>a\nb</key>\r\n\t<string>\n</string>\r\n\t<string a=\"1\n2\"/>\r\n\t<!-- c\nd -->\r\n\t<![CDATA[e\nf]]>\r\n</dict>\r\n";
+        let converted = convert_xml_line_endings(source.as_bytes(), LineEnding::Crlf);
+        assert_eq!(String::from_utf8(converted.clone()).unwrap(), expected);
+        assert_eq!(convert_xml_line_endings(&converted, LineEnding::Lf), source.as_bytes());
+        // unterminated markup is copied
+        assert_eq!(convert_xml_line_endings(b"<a>\n<b", LineEnding::Crlf), b"<a>\r\n<b");
+    }
+
+    #[test]
+    fn test_ufo_line_endings() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        assert!(copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new()).is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        for relpath in list_text_files(&test_ufo_path).unwrap() {
+            let path = test_ufo_path.join(&relpath);
+            let contents = fs::read(&path).unwrap();
+            if relpath != Path::new("lib.plist") {
+                fs::write(&path, convert_line_endings(&contents, LineEnding::Crlf)).unwrap();
+            }
+        }
+        let data_path = test_ufo_path.join("data");
+        fs::create_dir(&data_path).unwrap();
+        fs::write(data_path.join("lib.plist"), "a\nb\n").unwrap();
+
+        // source file line endings are kept, new files use the dominant line ending
+        let line_endings =
+            UfoLineEndings::from_source(&test_ufo_path, LineEnding::Preserve).unwrap();
+        assert_eq!(line_endings.get(Path::new("glyphs/A_.glif")), LineEnding::Crlf);
+        assert_eq!(line_endings.get(Path::new("lib.plist")), LineEnding::Lf);
+        assert_eq!(line_endings.get(Path::new("glyphs/new.glif")), LineEnding::Crlf);
+
+        // norad writes LF line endings, so files with LF line endings are not converted
+        let line_endings = UfoLineEndings::from_source(&test_ufo_path, LineEnding::Lf).unwrap();
+        assert!(line_endings.apply(&test_ufo_path, &[]).is_ok());
+        let contents = fs::read(test_ufo_path.join("glyphs/A_.glif")).unwrap();
+        assert_eq!(detect_line_ending(&contents), Some(LineEnding::Crlf));
+
+        let lib_path = test_ufo_path.join("lib.plist");
+        let lib_contents = fs::read(&lib_path).unwrap();
+        fs::write(&lib_path, convert_line_endings(&lib_contents, LineEnding::Crlf)).unwrap();
+        let glif_path = test_ufo_path.join("glyphs/A_.glif");
+        let glif_contents = convert_line_endings(&fs::read(&glif_path).unwrap(), LineEnding::Lf);
+        fs::write(&glif_path, &glif_contents).unwrap();
+        let line_endings = UfoLineEndings { by_path: BTreeMap::new(), default: LineEnding::Lf };
+        let preserved = UfoLineEndings {
+            by_path: vec![(PathBuf::from("glyphs/A_.glif"), LineEnding::Crlf)]
+                .into_iter()
+                .collect(),
+            ..line_endings
+        };
+        // only the files with CRLF line endings are converted, and the lib.plist
+        // file with an LF line ending keeps the CRLF line breaks that norad does not write
+        let unchanged = vec![PathBuf::from("glyphs/A_.glif")];
+        assert!(preserved.apply(&test_ufo_path, &unchanged).is_ok());
+        assert_eq!(fs::read(&glif_path).unwrap(), glif_contents);
+        assert!(preserved.apply(&test_ufo_path, &[]).is_ok());
+        let contents = fs::read(&glif_path).unwrap();
+        assert_eq!(detect_line_ending(&contents), Some(LineEnding::Crlf));
+        let contents = fs::read(&lib_path).unwrap();
+        assert_eq!(detect_line_ending(&contents), Some(LineEnding::Crlf));
+
+        let line_endings = UfoLineEndings::from_source(&test_ufo_path, LineEnding::Crlf).unwrap();
+        assert!(line_endings.apply(&test_ufo_path, &[]).is_ok());
+        for relpath in &["glyphs/A_.glif", "glyphs.background/S_.closed.glif", "metainfo.plist"] {
+            let contents = fs::read(test_ufo_path.join(relpath)).unwrap();
+            assert_eq!(detect_line_ending(&contents), Some(LineEnding::Crlf));
+        }
+        // data files are not converted
+        assert_eq!(fs::read(data_path.join("lib.plist")).unwrap(), b"a\nb\n");
+    }
+}
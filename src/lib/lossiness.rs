@@ -0,0 +1,382 @@
+//! Detection of UFO source contents that norad does not roundtrip
+//!
+//! norad discards some source file contents without an error when a UFO source
+//! is loaded and saved.  The raw source files are scanned before formatting so
+//! that the contents are reported instead of silently discarded:
+//!
+//! - XML comments in glif and plist files
+//! - elements, CDATA sections, and leading or trailing whitespace in glif notes
+//! - unknown metainfo.plist and layerinfo.plist keys
+//! - glif and lib.plist object libs without an object with the same identifier
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rayon::prelude::*;
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::utils::{self, UfoFileType};
+
+const PUBLIC_OBJECT_LIBS_KEY: &str = "public.objectLibs";
+const METAINFO_KEYS: &[&str] = &["creator", "formatVersion", "formatVersionMinor"];
+const LAYERINFO_KEYS: &[&str] = &["color", "lib"];
+
+/// Source file contents that are discarded by the format roundtrip
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Loss {
+    pub(crate) path: PathBuf,
+    pub(crate) element: String,
+    pub(crate) reason: String,
+}
+
+impl Loss {
+    fn new(path: &Path, element: impl Into<String>, reason: impl Into<String>) -> Self {
+        Loss { path: path.into(), element: element.into(), reason: reason.into() }
+    }
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        write!(f, "{}: {}: {}", self.path.display(), self.element, self.reason)
+    }
+}
+
+/// Scan the glif and plist files of a UFO source for contents that are
+/// discarded by the format roundtrip.  Files that cannot be parsed are skipped
+/// because they fail to load.
+pub(crate) fn scan_ufo(ufopath: &Path) -> Result<Vec<Loss>> {
+    let relpaths = utils::list_files(ufopath).map_err(|e| Error::Io(ufopath.into(), e))?;
+    let mut losses: Vec<Loss> = relpaths
+        .par_iter()
+        .filter(|p| !p.starts_with("data") && !p.starts_with("images"))
+        .map(|relpath| {
+            let path = ufopath.join(relpath);
+            let filetype = match utils::get_ufo_file_type(relpath) {
+                Some(filetype) => filetype,
+                None => return Ok(Vec::new()),
+            };
+            let contents = fs::read(&path).map_err(|e| Error::Io(path.clone(), e))?;
+            Ok(scan_file(&path, &contents, filetype))
+        })
+        .collect::<Result<Vec<Vec<Loss>>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    losses.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(losses)
+}
+
+/// Returns the contents of a UFO source file that are discarded by the format roundtrip
+pub(crate) fn scan_file(path: &Path, contents: &[u8], filetype: UfoFileType) -> Vec<Loss> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    match filetype {
+        UfoFileType::Glif => scan_glif(path, contents),
+        // comments in designspace files are kept
+        UfoFileType::Features | UfoFileType::Designspace => Vec::new(),
+        _ => {
+            let mut losses = scan_xml_comments(path, contents);
+            let known_keys = match file_name {
+                "metainfo.plist" => Some(METAINFO_KEYS),
+                "layerinfo.plist" => Some(LAYERINFO_KEYS),
+                _ => None,
+            };
+            if let (Some(known_keys), Some(dict)) = (known_keys, read_dictionary(contents)) {
+                for key in dict.keys().filter(|k| !known_keys.contains(&k.as_str())) {
+                    losses.push(Loss::new(path, format!("key {}", key), "unknown key"));
+                }
+            }
+            if file_name == "lib.plist" {
+                // fontinfo guidelines are the only lib.plist objects with object libs
+                let identifiers = get_fontinfo_guideline_identifiers(path);
+                if let Some(dict) = read_dictionary(contents) {
+                    losses.extend(scan_object_libs(path, &dict, &identifiers));
+                }
+            }
+            losses
+        }
+    }
+}
+
+/// Returns the XML comments of a plist file
+fn scan_xml_comments(path: &Path, contents: &[u8]) -> Vec<Loss> {
+    let mut reader = Reader::from_reader(contents);
+    let mut buf = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut losses = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(start)) => stack.push(String::from_utf8_lossy(start.name()).into()),
+            Ok(Event::End(_)) => {
+                stack.pop();
+            }
+            Ok(Event::Comment(_)) => losses.push(comment_loss(path, &stack)),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    losses
+}
+
+fn scan_glif(path: &Path, contents: &[u8]) -> Vec<Loss> {
+    let mut reader = Reader::from_reader(contents);
+    let mut buf = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut losses = Vec::new();
+    let mut identifiers = HashSet::new();
+    let mut lib_range = None;
+    loop {
+        let in_note = stack.last().map(|name| name == "note").unwrap_or(false);
+        let element = stack.join("/");
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(start)) => {
+                if in_note {
+                    losses.push(Loss::new(path, element, "note elements are not preserved"));
+                }
+                collect_identifier(&start, &mut identifiers);
+                let name = String::from_utf8_lossy(start.name()).into_owned();
+                if name == "lib" && stack.len() == 1 {
+                    lib_range = Some((reader.buffer_position(), reader.buffer_position()));
+                }
+                stack.push(name);
+            }
+            Ok(Event::Empty(start)) => {
+                if in_note {
+                    losses.push(Loss::new(path, element, "note elements are not preserved"));
+                }
+                collect_identifier(&start, &mut identifiers);
+            }
+            Ok(Event::End(end)) => {
+                if end.name() == b"lib" && stack.len() == 2 {
+                    if let Some((start, _)) = lib_range {
+                        let end = reader.buffer_position() - end.name().len() - 3;
+                        lib_range = Some((start, end));
+                    }
+                }
+                stack.pop();
+            }
+            Ok(Event::Text(text)) if in_note => {
+                let text = String::from_utf8_lossy(text.escaped());
+                if text.trim() != text && !text.trim().is_empty() {
+                    losses.push(Loss::new(
+                        path,
+                        element,
+                        "leading and trailing note whitespace is not preserved",
+                    ));
+                }
+            }
+            Ok(Event::CData(_)) if in_note => {
+                losses.push(Loss::new(path, element, "note CDATA sections are not preserved"));
+            }
+            Ok(Event::Comment(_)) => losses.push(comment_loss(path, &stack)),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    let lib = lib_range.and_then(|(start, end)| {
+        let lib_xml = contents.get(start..end)?;
+        plist::Value::from_reader_xml(lib_xml).ok()?.into_dictionary()
+    });
+    if let Some(lib) = lib {
+        losses.extend(scan_object_libs(path, &lib, &identifiers));
+    }
+    losses
+}
+
+fn comment_loss(path: &Path, stack: &[String]) -> Loss {
+    let element = match stack.is_empty() {
+        true => "document".to_string(),
+        false => stack.join("/"),
+    };
+    Loss::new(path, element, "XML comments are not preserved")
+}
+
+fn collect_identifier(start: &quick_xml::events::BytesStart, identifiers: &mut HashSet<String>) {
+    for attr in start.attributes().flatten() {
+        if attr.key == b"identifier" {
+            identifiers.insert(String::from_utf8_lossy(&attr.value).into_owned());
+        }
+    }
+}
+
+/// Returns the object libs that do not have an object with the same identifier
+fn scan_object_libs(
+    path: &Path,
+    lib: &plist::Dictionary,
+    identifiers: &HashSet<String>,
+) -> Vec<Loss> {
+    let object_libs = match lib.get(PUBLIC_OBJECT_LIBS_KEY).and_then(|v| v.as_dictionary()) {
+        Some(object_libs) => object_libs,
+        None => return Vec::new(),
+    };
+    object_libs
+        .keys()
+        .filter(|id| !identifiers.contains(id.as_str()))
+        .map(|id| {
+            Loss::new(
+                path,
+                format!("{} {}", PUBLIC_OBJECT_LIBS_KEY, id),
+                "object libs without an object with the same identifier are not preserved",
+            )
+        })
+        .collect()
+}
+
+fn read_dictionary(contents: &[u8]) -> Option<plist::Dictionary> {
+    plist::Value::from_reader(std::io::Cursor::new(contents)).ok()?.into_dictionary()
+}
+
+/// Returns the guideline identifiers in the fontinfo.plist file next to `lib_path`
+fn get_fontinfo_guideline_identifiers(lib_path: &Path) -> HashSet<String> {
+    let fontinfo_path = lib_path.with_file_name("fontinfo.plist");
+    let guidelines = fs::read(fontinfo_path)
+        .ok()
+        .and_then(|contents| read_dictionary(&contents))
+        .and_then(|mut dict| dict.remove("guidelines"))
+        .and_then(|guidelines| guidelines.into_array())
+        .unwrap_or_default();
+    guidelines
+        .iter()
+        .filter_map(|g| g.as_dictionary()?.get("identifier")?.as_string().map(String::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fs_extra::dir::{copy, CopyOptions};
+
+    const GLIF_PREFIX: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+    fn get_reasons(losses: &[Loss]) -> Vec<(&str, &str)> {
+        losses.iter().map(|l| (l.element.as_str(), l.reason.as_str())).collect()
+    }
+
+    #[test]
+    fn test_scan_glif_comments() {
+        let glif = format!(
+            "{}<!-- header -->\n<glyph name=\"a\" format=\"2\">\n  <outline>\n    <!-- contour -->\n  </outline>\n</glyph>\n",
+            GLIF_PREFIX
+        );
+        let losses = scan_file(Path::new("a.glif"), glif.as_bytes(), UfoFileType::Glif);
+        assert_eq!(
+            get_reasons(&losses),
+            vec![
+                ("document", "XML comments are not preserved"),
+                ("glyph/outline", "XML comments are not preserved")
+            ]
+        );
+        assert_eq!(
+            losses[0].to_string(),
+            "a.glif: document: XML comments are not preserved".to_string()
+        );
+    }
+
+    #[test]
+    fn test_scan_glif_note() {
+        let glif = format!(
+            "{}<glyph name=\"a\" format=\"2\">\n  <note>  text <b>bold</b></note>\n</glyph>\n",
+            GLIF_PREFIX
+        );
+        let losses = scan_file(Path::new("a.glif"), glif.as_bytes(), UfoFileType::Glif);
+        assert_eq!(
+            get_reasons(&losses),
+            vec![
+                ("glyph/note", "leading and trailing note whitespace is not preserved"),
+                ("glyph/note", "note elements are not preserved")
+            ]
+        );
+        let glif = format!(
+            "{}<glyph name=\"a\" format=\"2\">\n  <note>text</note>\n</glyph>\n",
+            GLIF_PREFIX
+        );
+        assert!(scan_file(Path::new("a.glif"), glif.as_bytes(), UfoFileType::Glif).is_empty());
+    }
+
+    #[test]
+    fn test_scan_glif_object_libs() {
+        let glif = format!(
+            "{}<glyph name=\"a\" format=\"2\">
+  <anchor x=\"0\" y=\"0\" name=\"top\" identifier=\"anchor1\"/>
+  <lib>
+    <dict>
+      <key>public.objectLibs</key>
+      <dict>
+        <key>anchor1</key>
+        <dict/>
+        <key>missing</key>
+        <dict/>
+      </dict>
+    </dict>
+  </lib>
+</glyph>
+",
+            GLIF_PREFIX
+        );
+        let losses = scan_file(Path::new("a.glif"), glif.as_bytes(), UfoFileType::Glif);
+        assert_eq!(
+            get_reasons(&losses),
+            vec![(
+                "public.objectLibs missing",
+                "object libs without an object with the same identifier are not preserved"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_scan_plist() {
+        let plist = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<plist version=\"1.0\">
+  <dict>
+    <!-- private -->
+    <key>creator</key>
+    <string>org.example</string>
+    <key>formatVersion</key>
+    <integer>3</integer>
+    <key>com.example.private</key>
+    <string>value</string>
+  </dict>
+</plist>
+";
+        let losses =
+            scan_file(Path::new("metainfo.plist"), plist.as_bytes(), UfoFileType::FontPlist);
+        assert_eq!(
+            get_reasons(&losses),
+            vec![
+                ("plist/dict", "XML comments are not preserved"),
+                ("key com.example.private", "unknown key")
+            ]
+        );
+        // unknown keys are only reported in files with a fixed set of keys
+        let losses = scan_file(Path::new("lib.plist"), plist.as_bytes(), UfoFileType::FontPlist);
+        assert_eq!(get_reasons(&losses), vec![("plist/dict", "XML comments are not preserved")]);
+    }
+
+    #[test]
+    fn test_scan_ufo() {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        assert_eq!(scan_ufo(src_ufo_path).unwrap(), vec![]);
+
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+        let glif_str = fs::read_to_string(&glif_path).unwrap();
+        fs::write(&glif_path, glif_str.replacen("<outline>", "<outline><!-- a -->", 1)).unwrap();
+        // data files are not scanned
+        fs::create_dir(test_ufo_path.join("data")).unwrap();
+        fs::write(test_ufo_path.join("data").join("lib.plist"), "<!-- a -->").unwrap();
+        let losses = scan_ufo(&test_ufo_path).unwrap();
+        assert_eq!(
+            losses,
+            vec![Loss::new(&glif_path, "glyph/outline", "XML comments are not preserved")]
+        );
+    }
+}
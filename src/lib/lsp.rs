@@ -0,0 +1,519 @@
+//! Language Server Protocol server for UFO source file formatting
+//!
+//! The server communicates over stdio with JSON-RPC messages that use the
+//! LSP `Content-Length` header framing.  It implements full document sync,
+//! `textDocument/formatting`, and publishes parse error diagnostics for
+//! glif, plist, and feature file documents.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::lib::formatters::{self, FormatOptions};
+use crate::lib::utils;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+// LSP diagnostic severity
+const SEVERITY_ERROR: u8 = 1;
+// LSP text document sync kind
+const SYNC_FULL: u8 = 1;
+
+struct Server {
+    /// Formatting options for all documents served by the language server
+    options: FormatOptions,
+    /// Open document text by URI
+    documents: HashMap<String, String>,
+    shutdown_requested: bool,
+}
+
+/// Run the language server until the client sends the exit notification or
+/// closes the input stream. Returns the process exit code.
+pub(crate) fn run<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    options: FormatOptions,
+) -> io::Result<i32> {
+    let mut server = Server { options, documents: HashMap::new(), shutdown_requested: false };
+
+    while let Some(body) = read_message(reader)? {
+        let message: Value = match serde_json::from_str(&body) {
+            Ok(m) => m,
+            Err(e) => {
+                let response = error_response(Value::Null, PARSE_ERROR, &e.to_string());
+                write_message(writer, &response)?;
+                continue;
+            }
+        };
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(m) => m,
+            // responses to server initiated requests are not used
+            None if message.get("id").is_some() => continue,
+            None => {
+                let response = error_response(Value::Null, INVALID_REQUEST, "missing method");
+                write_message(writer, &response)?;
+                continue;
+            }
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        if method == "exit" {
+            return Ok(if server.shutdown_requested { 0 } else { 1 });
+        }
+
+        match message.get("id").cloned() {
+            Some(id) => {
+                let response = match server.handle_request(method, &params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, msg)) => error_response(id, code, &msg),
+                };
+                write_message(writer, &response)?;
+            }
+            None => {
+                for notification in server.handle_notification(method, &params) {
+                    write_message(writer, &notification)?;
+                }
+            }
+        }
+    }
+    Ok(if server.shutdown_requested { 0 } else { 1 })
+}
+
+impl Server {
+    fn handle_request(
+        &mut self,
+        method: &str,
+        params: &Value,
+    ) -> std::result::Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": {
+                    "name": "ufofmt",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => {
+                let uri = document_uri(params)
+                    .ok_or_else(|| (INVALID_PARAMS, "missing textDocument uri".to_string()))?;
+                let text = self
+                    .documents
+                    .get(uri)
+                    .ok_or_else(|| (INVALID_PARAMS, format!("document is not open: {}", uri)))?;
+                let formatted = self.format(uri, text).map_err(|e| (INTERNAL_ERROR, e))?;
+                if &formatted == text {
+                    return Ok(json!([]));
+                }
+                Ok(json!([{"range": full_range(text), "newText": formatted}]))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method: {}", method))),
+        }
+    }
+
+    /// Returns the notifications that the server sends in response
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = match document_uri(params) {
+            Some(uri) => uri.to_string(),
+            None => return Vec::new(),
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                self.diagnostics(&uri).into_iter().collect()
+            }
+            "textDocument/didChange" => {
+                // full document sync: the last change holds the complete text
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()).and_then(|c| c["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                self.diagnostics(&uri).into_iter().collect()
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                // clear the diagnostics of the closed document
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn format(&self, uri: &str, text: &str) -> std::result::Result<String, String> {
+        let filepath = uri_to_path(uri);
+        let formatted = formatters::format_file_contents(&filepath, text.as_bytes(), &self.options)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(formatted).map_err(|e| e.to_string())
+    }
+
+    /// Parse error diagnostics for supported document types
+    fn diagnostics(&self, uri: &str) -> Option<Value> {
+        utils::get_ufo_file_type(&uri_to_path(uri))?;
+        let text = self.documents.get(uri)?;
+        let diagnostics = match self.format(uri, text) {
+            Ok(_) => Vec::new(),
+            Err(msg) => vec![json!({
+                "range": {
+                    "start": {"line": 0, "character": 0},
+                    "end": {"line": 0, "character": 0},
+                },
+                "severity": SEVERITY_ERROR,
+                "source": "ufofmt",
+                "message": msg,
+            })],
+        };
+        Some(publish_diagnostics(uri, diagnostics))
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+/// Range that spans the complete document text
+fn full_range(text: &str) -> Value {
+    let line_count = text.matches('\n').count();
+    let last_line = text.rsplit('\n').next().unwrap_or_default();
+    // LSP character offsets are defined in UTF-16 code units
+    let last_character = last_line.encode_utf16().count();
+    json!({
+        "start": {"line": 0, "character": 0},
+        "end": {"line": line_count, "character": last_character},
+    })
+}
+
+/// Convert a document URI to a path. Only the path component of the URI is
+/// used to infer the file type, so non-file schemes are accepted.
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = match uri.find("://") {
+        Some(idx) => &uri[idx + 3..],
+        None => uri,
+    };
+    // strip the authority component
+    let path = match path.find('/') {
+        Some(idx) => &path[idx..],
+        None => path,
+    };
+    PathBuf::from(percent_decode(path))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Read a single `Content-Length` framed message body. Returns None at the
+/// end of the input stream.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+
+    fn default_options() -> FormatOptions {
+        FormatOptions::default()
+    }
+
+    /// Frame client messages into an input stream
+    fn client_input(messages: &[Value]) -> Cursor<Vec<u8>> {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        Cursor::new(input)
+    }
+
+    /// Parse all server messages from an output stream
+    fn server_output(output: Vec<u8>) -> Vec<Value> {
+        let mut reader = Cursor::new(output);
+        let mut messages = Vec::new();
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            messages.push(serde_json::from_str(&body).unwrap());
+        }
+        messages
+    }
+
+    fn run_session(messages: &[Value], options: FormatOptions) -> (i32, Vec<Value>) {
+        let mut input = client_input(messages);
+        let mut output = Vec::new();
+        let exit_code = run(&mut input, &mut output, options).unwrap();
+        (exit_code, server_output(output))
+    }
+
+    fn did_open(uri: &str, text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": uri, "languageId": "xml", "version": 1, "text": text}},
+        })
+    }
+
+    fn formatting(id: u64, uri: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "textDocument/formatting",
+            "params": {"textDocument": {"uri": uri}, "options": {"tabSize": 4, "insertSpaces": true}},
+        })
+    }
+
+    fn lifecycle_start() -> Value {
+        json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {"capabilities": {}}})
+    }
+
+    fn lifecycle_end() -> Vec<Value> {
+        vec![
+            json!({"jsonrpc": "2.0", "id": 99, "method": "shutdown"}),
+            json!({"jsonrpc": "2.0", "method": "exit"}),
+        ]
+    }
+
+    #[test]
+    fn test_lsp_initialize_and_shutdown() {
+        let mut messages = vec![lifecycle_start()];
+        messages.push(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}));
+        messages.extend(lifecycle_end());
+        let (exit_code, output) = run_session(&messages, default_options());
+        assert_eq!(exit_code, 0);
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0]["id"], 0);
+        assert_eq!(output[0]["result"]["capabilities"]["documentFormattingProvider"], true);
+        assert_eq!(output[0]["result"]["capabilities"]["textDocumentSync"], 1);
+        assert_eq!(output[1], json!({"jsonrpc": "2.0", "id": 99, "result": null}));
+    }
+
+    #[test]
+    fn test_lsp_exit_without_shutdown() {
+        let messages = vec![lifecycle_start(), json!({"jsonrpc": "2.0", "method": "exit"})];
+        let (exit_code, _) = run_session(&messages, default_options());
+        assert_eq!(exit_code, 1);
+    }
+
+    #[test]
+    fn test_lsp_format_glif() {
+        let uri = "file:///fonts/Test%20Sans.ufo/glyphs/A_.glif";
+        let source =
+            fs::read_to_string("testdata/ufo/MutatorSansBoldCondensed.ufo/glyphs/A_.glif").unwrap();
+        let expected = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        let mut messages = vec![lifecycle_start(), did_open(uri, &source), formatting(1, uri)];
+        messages.extend(lifecycle_end());
+        let (exit_code, output) = run_session(&messages, default_options());
+        assert_eq!(exit_code, 0);
+
+        // no diagnostics for a valid glif
+        assert_eq!(output[1]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(output[1]["params"]["uri"], uri);
+        assert_eq!(output[1]["params"]["diagnostics"], json!([]));
+
+        let edits = output[2]["result"].as_array().unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0]["newText"], expected.as_str());
+        assert_eq!(edits[0]["range"], full_range(&source));
+    }
+
+    #[test]
+    fn test_lsp_format_formatted_document_no_edits() {
+        let uri = "file:///fonts/Test.ufo/glyphs/A_.glif";
+        let source = fs::read_to_string(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif",
+        )
+        .unwrap();
+        let messages = vec![lifecycle_start(), did_open(uri, &source), formatting(1, uri)];
+        let (_, output) = run_session(&messages, default_options());
+        assert_eq!(output[2]["result"], json!([]));
+    }
+
+    #[test]
+    fn test_lsp_format_uses_server_options() {
+        let uri = "file:///fonts/Test.ufo/metainfo.plist";
+        let source =
+            fs::read_to_string("testdata/ufo/MutatorSansBoldCondensed.ufo/metainfo.plist").unwrap();
+        let options = FormatOptions {
+            singlequotes: true,
+            indent_with_space: true,
+            indent_number: 2,
+            ..Default::default()
+        };
+        let messages = vec![lifecycle_start(), did_open(uri, &source), formatting(1, uri)];
+        let (_, output) = run_session(&messages, options);
+        let new_text = output[2]["result"][0]["newText"].as_str().unwrap();
+        assert!(new_text.starts_with("<?xml version='1.0' encoding='UTF-8'?>"));
+        assert!(new_text.contains("\n  <key>creator</key>"));
+    }
+
+    #[test]
+    fn test_lsp_diagnostics_on_parse_error_and_change() {
+        let uri = "file:///fonts/Test.ufo/glyphs/A_.glif";
+        let valid = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<glyph name=\"A\" format=\"2\">\n</glyph>\n";
+        let messages = vec![
+            lifecycle_start(),
+            did_open(uri, "<glyph"),
+            formatting(1, uri),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didChange",
+                "params": {
+                    "textDocument": {"uri": uri, "version": 2},
+                    "contentChanges": [{"text": valid}],
+                },
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didClose",
+                "params": {"textDocument": {"uri": uri}},
+            }),
+        ];
+        let (_, output) = run_session(&messages, default_options());
+
+        let diagnostics = output[1]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["source"], "ufofmt");
+        assert!(diagnostics[0]["message"].as_str().unwrap().starts_with("norad read error: "));
+
+        // formatting requests fail with the same error
+        assert_eq!(output[2]["id"], 1);
+        assert_eq!(output[2]["error"]["code"], INTERNAL_ERROR);
+
+        // diagnostics are cleared after a valid change and on close
+        assert_eq!(output[3]["params"]["diagnostics"], json!([]));
+        assert_eq!(output[4]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_lsp_unsupported_document_and_method() {
+        let uri = "file:///notes/readme.txt";
+        let messages = vec![
+            lifecycle_start(),
+            did_open(uri, "text"),
+            formatting(1, uri),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {}}),
+            formatting(3, "file:///not/open.glif"),
+        ];
+        let (_, output) = run_session(&messages, default_options());
+        // no diagnostics are published for unsupported documents
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[1]["error"]["code"], INTERNAL_ERROR);
+        assert!(output[1]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("unsupported file type error: "));
+        assert_eq!(output[2]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(output[3]["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_lsp_invalid_json() {
+        let body = "{not json";
+        let input = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let mut output = Vec::new();
+        let exit_code =
+            run(&mut Cursor::new(input.into_bytes()), &mut output, default_options()).unwrap();
+        assert_eq!(exit_code, 1);
+        let output = server_output(output);
+        assert_eq!(output[0]["error"]["code"], PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(
+            uri_to_path("file:///fonts/Test%20Sans.ufo/glyphs/A_.glif"),
+            PathBuf::from("/fonts/Test Sans.ufo/glyphs/A_.glif")
+        );
+        assert_eq!(
+            uri_to_path("file://host/share/Test.ufo/lib.plist"),
+            PathBuf::from("/share/Test.ufo/lib.plist")
+        );
+        assert_eq!(uri_to_path("untitled:A_.glif"), PathBuf::from("untitled:A_.glif"));
+    }
+
+    #[test]
+    fn test_full_range() {
+        assert_eq!(
+            full_range("ab\ncd"),
+            json!({"start": {"line": 0, "character": 0}, "end": {"line": 1, "character": 2}})
+        );
+        assert_eq!(
+            full_range("ab\n"),
+            json!({"start": {"line": 0, "character": 0}, "end": {"line": 1, "character": 0}})
+        );
+    }
+}
@@ -1,3 +1,17 @@
+pub mod backup;
+pub mod config;
+pub mod designspace;
+pub mod discovery;
 pub mod errors;
+pub mod fea;
 pub mod formatters;
+pub mod git;
+pub mod glyphs;
+pub mod lenient;
+pub mod line_endings;
+pub mod lossiness;
+pub mod lsp;
+pub mod reports;
+pub mod stores;
 pub mod utils;
+pub mod verify;
@@ -0,0 +1,681 @@
+//! Machine-readable formatting reports
+//!
+//! The per-UFO formatting results are converted to findings with a file path,
+//! an optional line number, and a message.  The findings are written as a
+//! SARIF 2.1.0 log or as GitHub Actions workflow commands so that they are
+//! displayed inline in pull request reviews.  The per-UFO results are also
+//! written as JUnit XML test cases for CI test dashboards.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use quick_xml::escape::escape;
+use serde_json::{json, Value};
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::formatters::FormatSummary;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Formatting result output format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OutputFormat {
+    /// Human-readable status lines
+    Text,
+    /// SARIF 2.1.0 log
+    Sarif,
+    /// GitHub Actions workflow commands
+    Github,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "github" => Ok(OutputFormat::Github),
+            _ => Err(format!("unsupported output format: {}", s)),
+        }
+    }
+}
+
+/// Finding severity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    /// Returns the SARIF result level
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        }
+    }
+
+    /// Returns the GitHub Actions workflow command name
+    fn github_command(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "notice",
+        }
+    }
+}
+
+/// Finding rule identifiers and descriptions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Rule {
+    Unformatted,
+    Reformatted,
+    InvalidFile,
+    DiscardedContents,
+    UnparsableGlif,
+}
+
+const RULES: &[Rule] = &[
+    Rule::Unformatted,
+    Rule::Reformatted,
+    Rule::InvalidFile,
+    Rule::DiscardedContents,
+    Rule::UnparsableGlif,
+];
+
+impl Rule {
+    fn id(&self) -> &'static str {
+        match self {
+            Rule::Unformatted => "unformatted",
+            Rule::Reformatted => "reformatted",
+            Rule::InvalidFile => "invalid-file",
+            Rule::DiscardedContents => "discarded-contents",
+            Rule::UnparsableGlif => "unparsable-glif",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Rule::Unformatted => "UFO source file is not formatted",
+            Rule::Reformatted => "UFO source file was reformatted",
+            Rule::InvalidFile => "UFO source or file cannot be read or written",
+            Rule::DiscardedContents => "Formatting discards UFO source file contents",
+            Rule::UnparsableGlif => "glif file cannot be parsed and is not formatted",
+        }
+    }
+}
+
+/// Formatting result for a single file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Finding {
+    pub(crate) rule: Rule,
+    pub(crate) level: Level,
+    /// file or UFO source path, not defined for errors without a path
+    pub(crate) path: Option<PathBuf>,
+    /// 1-based line number
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+/// Returns the findings of the per-UFO formatting results in result order.
+/// `check` is true when files are checked without writing them, with the
+/// `--check` and `--dry-run` options.  Changed files are errors in checks and
+/// notes about the rewritten files otherwise.
+pub(crate) fn get_findings(results: &[Result<FormatSummary>], check: bool) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for result in results {
+        match result {
+            Ok(summary) => {
+                findings.extend(summary.changed.iter().map(|path| match check {
+                    true => Finding {
+                        rule: Rule::Unformatted,
+                        level: Level::Error,
+                        path: Some(path.clone()),
+                        line: None,
+                        message: get_unformatted_message(summary, path).to_string(),
+                    },
+                    false => Finding {
+                        rule: Rule::Reformatted,
+                        level: Level::Note,
+                        path: Some(path.clone()),
+                        line: None,
+                        message: get_reformatted_message(summary, path).to_string(),
+                    },
+                }));
+                findings.extend(summary.losses.iter().map(|loss| Finding {
+                    rule: Rule::DiscardedContents,
+                    level: Level::Warning,
+                    path: Some(loss.path.clone()),
+                    line: None,
+                    message: format!("discarded {}: {}", loss.element, loss.reason),
+                }));
+                findings.extend(summary.skipped_glifs.iter().map(|glif| Finding {
+                    rule: Rule::UnparsableGlif,
+                    level: Level::Warning,
+                    path: Some(glif.path.clone()),
+                    line: None,
+                    message: format!("skipped glyph {}: {}", glif.glyph_name, glif.error),
+                }));
+            }
+            Err(err) => {
+                let (path, line) = get_error_location(err);
+                findings.push(Finding {
+                    rule: Rule::InvalidFile,
+                    level: Level::Error,
+                    path,
+                    line,
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+    findings
+}
+
+fn get_unformatted_message(summary: &FormatSummary, path: &Path) -> &'static str {
+    match summary.unwritten.iter().any(|p| p == path) {
+        true => "file is not written by formatting",
+        false => "file is not formatted",
+    }
+}
+
+fn get_reformatted_message(summary: &FormatSummary, path: &Path) -> &'static str {
+    match summary.unwritten.iter().any(|p| p == path) {
+        true => "file was removed by formatting",
+        false => "file was reformatted",
+    }
+}
+
+/// Returns the path and line of an error. glif parse errors are reported at
+/// the glif file line of the parse error, other errors at their path.
+fn get_error_location(err: &Error) -> (Option<PathBuf>, Option<usize>) {
+    if let Error::NoradRead(_, norad::Error::Glif(glif_err)) = err {
+        if let Some(glif_path) = &glif_err.path {
+            let line = fs::read(glif_path).ok().map(|contents| {
+                let end = glif_err.position.min(contents.len());
+                contents[..end].iter().filter(|b| **b == b'\n').count() + 1
+            });
+            return (Some(glif_path.clone()), line);
+        }
+    }
+    (err.path().map(PathBuf::from), None)
+}
+
+/// Returns a SARIF 2.1.0 log with one result per finding
+pub(crate) fn format_sarif(findings: &[Finding]) -> String {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|rule| json!({"id": rule.id(), "shortDescription": {"text": rule.description()}}))
+        .collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            let mut result = json!({
+                "ruleId": finding.rule.id(),
+                "level": finding.level.as_str(),
+                "message": {"text": finding.message},
+            });
+            if let Some(path) = &finding.path {
+                let mut location = json!({"artifactLocation": {"uri": get_uri(path)}});
+                if let Some(line) = finding.line {
+                    location["region"] = json!({ "startLine": line });
+                }
+                result["locations"] = json!([{ "physicalLocation": location }]);
+            }
+            result
+        })
+        .collect();
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ufofmt",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/source-foundry/ufofmt",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+    serde_json::to_string_pretty(&log).unwrap_or_default()
+}
+
+/// Returns one GitHub Actions workflow command line per finding
+pub(crate) fn format_github(findings: &[Finding]) -> String {
+    let mut commands = String::new();
+    for finding in findings {
+        let mut properties = Vec::new();
+        if let Some(path) = &finding.path {
+            properties.push(format!("file={}", escape_property(&path.to_string_lossy())));
+        }
+        if let Some(line) = finding.line {
+            properties.push(format!("line={}", line));
+        }
+        properties.push(format!("title={}", escape_property(finding.rule.description())));
+        commands.push_str(&format!(
+            "::{} {}::{}\n",
+            finding.level.github_command(),
+            properties.join(","),
+            escape_data(&finding.message)
+        ));
+    }
+    commands
+}
+
+/// Returns a JUnit XML report of the per-UFO results.  `ufopaths`, `results`,
+/// and `durations` are in the same order.  Each UFO source is a test case, or a
+/// test suite with one test case per source file when `per_file` is true.
+pub(crate) fn format_junit(
+    ufopaths: &[PathBuf],
+    results: &[Result<FormatSummary>],
+    durations: &[Duration],
+    per_file: bool,
+) -> String {
+    let mut suites = Vec::new();
+    if per_file {
+        for ((ufopath, result), duration) in ufopaths.iter().zip(results).zip(durations) {
+            let ufo_name = ufopath.display().to_string();
+            let testcases = match result {
+                Ok(summary) => {
+                    let paths: BTreeSet<&PathBuf> =
+                        summary.checked.iter().chain(summary.changed.iter()).collect();
+                    paths
+                        .into_iter()
+                        .map(|path| {
+                            let name = path.strip_prefix(ufopath).unwrap_or(path);
+                            let failure = match summary.changed.contains(path) {
+                                true => Some((
+                                    Rule::Unformatted,
+                                    get_unformatted_message(summary, path).to_string(),
+                                )),
+                                false => None,
+                            };
+                            JunitTestcase {
+                                classname: ufo_name.clone(),
+                                name: name.display().to_string(),
+                                time: None,
+                                failure,
+                            }
+                        })
+                        .collect()
+                }
+                Err(err) => vec![JunitTestcase {
+                    classname: ufo_name.clone(),
+                    name: ufo_name.clone(),
+                    time: None,
+                    failure: Some((Rule::InvalidFile, err.to_string())),
+                }],
+            };
+            suites.push((ufo_name, *duration, testcases));
+        }
+    } else {
+        let testcases = ufopaths
+            .iter()
+            .zip(results)
+            .zip(durations)
+            .map(|((ufopath, result), duration)| JunitTestcase {
+                classname: "ufofmt".to_string(),
+                name: ufopath.display().to_string(),
+                time: Some(*duration),
+                failure: result.as_ref().err().map(|err| (Rule::InvalidFile, err.to_string())),
+            })
+            .collect();
+        suites.push(("ufofmt".to_string(), durations.iter().sum(), testcases));
+    }
+
+    let total_tests: usize = suites.iter().map(|(_, _, testcases)| testcases.len()).sum();
+    let total_failures: usize = suites
+        .iter()
+        .flat_map(|(_, _, testcases)| testcases)
+        .filter(|t| t.failure.is_some())
+        .count();
+    let total_time: Duration = suites.iter().map(|(_, time, _)| *time).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"ufofmt\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\">\n",
+        total_tests,
+        total_failures,
+        format_seconds(total_time)
+    ));
+    for (name, time, testcases) in &suites {
+        let failures = testcases.iter().filter(|t| t.failure.is_some()).count();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\">\n",
+            escape_xml(name),
+            testcases.len(),
+            failures,
+            format_seconds(*time)
+        ));
+        for testcase in testcases {
+            xml.push_str(&testcase.to_xml());
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// JUnit XML test case
+struct JunitTestcase {
+    classname: String,
+    name: String,
+    time: Option<Duration>,
+    /// failure rule and message
+    failure: Option<(Rule, String)>,
+}
+
+impl JunitTestcase {
+    fn to_xml(&self) -> String {
+        let time = match self.time {
+            Some(time) => format!(" time=\"{}\"", format_seconds(time)),
+            None => String::new(),
+        };
+        let attrs = format!(
+            "classname=\"{}\" name=\"{}\"{}",
+            escape_xml(&self.classname),
+            escape_xml(&self.name),
+            time
+        );
+        match &self.failure {
+            Some((rule, message)) => format!(
+                "    <testcase {}>\n      <failure type=\"{}\" message=\"{}\">{}</failure>\n    </testcase>\n",
+                attrs,
+                rule.id(),
+                escape_xml(message),
+                escape_xml(message)
+            ),
+            None => format!("    <testcase {}/>\n", attrs),
+        }
+    }
+}
+
+fn format_seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn escape_xml(s: &str) -> String {
+    String::from_utf8_lossy(&escape(s.as_bytes())).into_owned()
+}
+
+/// Relative paths are written as relative URI references with forward slash
+/// separators, absolute paths as file URIs
+fn get_uri(path: &Path) -> String {
+    let uri_path: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(s) => Some(percent_encode(&s.to_string_lossy())),
+            std::path::Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect();
+    match path.is_absolute() {
+        true => format!("file:///{}", uri_path.join("/")),
+        false => uri_path.join("/"),
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::lib::lossiness::Loss;
+
+    fn get_test_results() -> Vec<Result<FormatSummary>> {
+        vec![
+            Ok(FormatSummary {
+                source: PathBuf::from("Test.ufo"),
+                outpath: PathBuf::from("Test.ufo"),
+                unwritten: vec![PathBuf::from("Test.ufo/.DS_Store")],
+                changed: vec![
+                    PathBuf::from("Test.ufo/.DS_Store"),
+                    PathBuf::from("Test.ufo/glyphs/A_.glif"),
+                ],
+                losses: vec![Loss {
+                    path: PathBuf::from("Test.ufo/lib.plist"),
+                    element: "plist/dict".to_string(),
+                    reason: "XML comments are not preserved".to_string(),
+                }],
+                ..Default::default()
+            }),
+            Err(Error::InvalidPath(PathBuf::from("Bogus.ufo"))),
+        ]
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!(OutputFormat::from_str("text"), Ok(OutputFormat::Text));
+        assert_eq!(OutputFormat::from_str("sarif"), Ok(OutputFormat::Sarif));
+        assert_eq!(OutputFormat::from_str("github"), Ok(OutputFormat::Github));
+        assert_eq!(
+            OutputFormat::from_str("xml"),
+            Err("unsupported output format: xml".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_findings() {
+        let findings = get_findings(&get_test_results(), true);
+        let summary: Vec<(Rule, Option<&str>, &str)> = findings
+            .iter()
+            .map(|f| (f.rule, f.path.as_ref().and_then(|p| p.to_str()), f.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Rule::Unformatted,
+                    Some("Test.ufo/.DS_Store"),
+                    "file is not written by formatting"
+                ),
+                (Rule::Unformatted, Some("Test.ufo/glyphs/A_.glif"), "file is not formatted"),
+                (
+                    Rule::DiscardedContents,
+                    Some("Test.ufo/lib.plist"),
+                    "discarded plist/dict: XML comments are not preserved"
+                ),
+                (
+                    Rule::InvalidFile,
+                    Some("Bogus.ufo"),
+                    "invalid path error: Bogus.ufo was not found"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_findings_format_mode() {
+        let findings = get_findings(&get_test_results(), false);
+        let changed: Vec<(Rule, Level, &str)> = findings
+            .iter()
+            .filter(|f| f.rule != Rule::DiscardedContents && f.rule != Rule::InvalidFile)
+            .map(|f| (f.rule, f.level, f.message.as_str()))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                (Rule::Reformatted, Level::Note, "file was removed by formatting"),
+                (Rule::Reformatted, Level::Note, "file was reformatted"),
+            ]
+        );
+        // errors are reported in format mode too
+        assert_eq!(findings.last().unwrap().level, Level::Error);
+
+        let log: Value = serde_json::from_str(&format_sarif(&findings)).unwrap();
+        assert_eq!(log["runs"][0]["results"][1]["ruleId"], "reformatted");
+        assert_eq!(log["runs"][0]["results"][1]["level"], "note");
+        assert!(format_github(&findings).starts_with(
+            "::notice file=Test.ufo/.DS_Store,title=UFO source file was reformatted::"
+        ));
+    }
+
+    #[test]
+    fn test_get_findings_glif_parse_error_line() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        crate::lib::utils::copy_dir_all(src_ufo_path, &test_ufo_path).unwrap();
+        let glif_path = test_ufo_path.join("glyphs").join("B_.glif");
+        let glif_str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<glyph name=\"B\" format=\"2\">\n  <advance width=\"x\"/>\n</glyph>\n";
+        fs::write(&glif_path, glif_str).unwrap();
+        let err = norad::Font::load(&test_ufo_path)
+            .map_err(|e| Error::NoradRead(test_ufo_path.clone(), e))
+            .unwrap_err();
+        let findings = get_findings(&[Err(err)], true);
+        assert_eq!(findings[0].path, Some(glif_path));
+        assert_eq!(findings[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_format_sarif() {
+        let sarif = format_sarif(&get_findings(&get_test_results(), true));
+        let log: Value = serde_json::from_str(&sarif).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["runs"][0]["tool"]["driver"]["name"], "ufofmt");
+        assert_eq!(log["runs"][0]["tool"]["driver"]["rules"].as_array().unwrap().len(), 5);
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[1]["ruleId"], "unformatted");
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "Test.ufo/glyphs/A_.glif"
+        );
+        assert!(results[1]["locations"][0]["physicalLocation"].get("region").is_none());
+        assert_eq!(results[2]["level"], "warning");
+
+        let findings = vec![Finding {
+            rule: Rule::InvalidFile,
+            level: Level::Error,
+            path: None,
+            line: None,
+            message: "error".to_string(),
+        }];
+        let log: Value = serde_json::from_str(&format_sarif(&findings)).unwrap();
+        assert!(log["runs"][0]["results"][0].get("locations").is_none());
+    }
+
+    #[test]
+    fn test_format_github() {
+        let commands = format_github(&get_findings(&get_test_results(), true));
+        assert_eq!(
+            commands.lines().collect::<Vec<&str>>(),
+            vec![
+                "::error file=Test.ufo/.DS_Store,title=UFO source file is not formatted::file is not written by formatting",
+                "::error file=Test.ufo/glyphs/A_.glif,title=UFO source file is not formatted::file is not formatted",
+                "::warning file=Test.ufo/lib.plist,title=Formatting discards UFO source file contents::discarded plist/dict: XML comments are not preserved",
+                "::error file=Bogus.ufo,title=UFO source or file cannot be read or written::invalid path error: Bogus.ufo was not found",
+            ]
+        );
+        let findings = vec![Finding {
+            rule: Rule::InvalidFile,
+            level: Level::Error,
+            path: Some(PathBuf::from("a,b:c.ufo")),
+            line: Some(2),
+            message: "100%\nfailed".to_string(),
+        }];
+        assert_eq!(
+            format_github(&findings),
+            "::error file=a%2Cb%3Ac.ufo,line=2,title=UFO source or file cannot be read or written::100%25%0Afailed\n"
+        );
+    }
+
+    #[test]
+    fn test_format_junit() {
+        let ufopaths = vec![PathBuf::from("Test.ufo"), PathBuf::from("Bogus.ufo")];
+        let durations = vec![Duration::from_millis(1250), Duration::from_millis(5)];
+        let junit = format_junit(&ufopaths, &get_test_results(), &durations, false);
+        assert_eq!(
+            junit,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"ufofmt\" tests=\"2\" failures=\"1\" errors=\"0\" time=\"1.255\">
+  <testsuite name=\"ufofmt\" tests=\"2\" failures=\"1\" errors=\"0\" time=\"1.255\">
+    <testcase classname=\"ufofmt\" name=\"Test.ufo\" time=\"1.250\"/>
+    <testcase classname=\"ufofmt\" name=\"Bogus.ufo\" time=\"0.005\">
+      <failure type=\"invalid-file\" message=\"invalid path error: Bogus.ufo was not found\">invalid path error: Bogus.ufo was not found</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"
+        );
+    }
+
+    #[test]
+    fn test_format_junit_per_file() {
+        let ufopaths = vec![PathBuf::from("Test.ufo"), PathBuf::from("Bogus.ufo")];
+        let mut results = get_test_results();
+        if let Ok(summary) = &mut results[0] {
+            summary.checked = vec![
+                PathBuf::from("Test.ufo/.DS_Store"),
+                PathBuf::from("Test.ufo/glyphs/A_.glif"),
+                PathBuf::from("Test.ufo/glyphs/B_.glif"),
+            ];
+        }
+        let durations = vec![Duration::from_millis(1250), Duration::from_millis(5)];
+        let junit = format_junit(&ufopaths, &results, &durations, true);
+        assert_eq!(
+            junit,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"ufofmt\" tests=\"4\" failures=\"3\" errors=\"0\" time=\"1.255\">
+  <testsuite name=\"Test.ufo\" tests=\"3\" failures=\"2\" errors=\"0\" time=\"1.250\">
+    <testcase classname=\"Test.ufo\" name=\".DS_Store\">
+      <failure type=\"unformatted\" message=\"file is not written by formatting\">file is not written by formatting</failure>
+    </testcase>
+    <testcase classname=\"Test.ufo\" name=\"glyphs/A_.glif\">
+      <failure type=\"unformatted\" message=\"file is not formatted\">file is not formatted</failure>
+    </testcase>
+    <testcase classname=\"Test.ufo\" name=\"glyphs/B_.glif\"/>
+  </testsuite>
+  <testsuite name=\"Bogus.ufo\" tests=\"1\" failures=\"1\" errors=\"0\" time=\"0.005\">
+    <testcase classname=\"Bogus.ufo\" name=\"Bogus.ufo\">
+      <failure type=\"invalid-file\" message=\"invalid path error: Bogus.ufo was not found\">invalid path error: Bogus.ufo was not found</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"
+        );
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn test_get_uri() {
+        assert_eq!(get_uri(Path::new("Test.ufo/glyphs/A_.glif")), "Test.ufo/glyphs/A_.glif");
+        assert_eq!(get_uri(Path::new("./My Font.ufo")), "My%20Font.ufo");
+        assert_eq!(get_uri(Path::new("../Test.ufo")), "../Test.ufo");
+        assert_eq!(get_uri(Path::new("/tmp/Test.ufo")), "file:///tmp/Test.ufo");
+    }
+}
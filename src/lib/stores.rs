@@ -0,0 +1,251 @@
+//! UFO source data and images directory handling
+//!
+//! norad copies the `data` and `images` directory files without changes.  The
+//! files are read when the UFO source is saved, after the UFO source directory
+//! was removed, so store entries are validated before the UFO source is written.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use norad::Font;
+
+use crate::lib::errors::{Error, Result};
+
+const PNG_SIGNATURE: &[u8] = &[137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Validate the data and image store entries of a UFO source. Image files are
+/// fully validated as PNG files when `verify_images` is true.
+pub(crate) fn validate_stores(ufo: &Font, ufopath: &Path, verify_images: bool) -> Result<()> {
+    for (data_path, contents) in ufo.data.iter() {
+        if let Err(e) = contents {
+            return Err(Error::InvalidData(ufopath.join("data").join(data_path), e.to_string()));
+        }
+    }
+    for (image_path, contents) in ufo.images.iter() {
+        let invalid =
+            |msg: String| Error::InvalidImage(ufopath.join("images").join(image_path), msg);
+        let data = contents.map_err(|e| invalid(e.to_string()))?;
+        if verify_images {
+            validate_png(&data).map_err(|msg| invalid(msg.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Validate the PNG file chunk structure and chunk checksums
+pub(crate) fn validate_png(data: &[u8]) -> std::result::Result<(), &'static str> {
+    let mut chunks = match data.strip_prefix(PNG_SIGNATURE) {
+        Some(chunks) => chunks,
+        None => return Err("missing PNG signature"),
+    };
+    let mut is_first = true;
+    loop {
+        if chunks.len() < 12 {
+            return Err("truncated PNG chunk");
+        }
+        let length = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+        if chunks.len() - 12 < length {
+            return Err("truncated PNG chunk");
+        }
+        let (chunk_type, chunk_data) = (&chunks[4..8], &chunks[8..8 + length]);
+        let crc = &chunks[8 + length..12 + length];
+        if crc32(&chunks[4..8 + length]).to_be_bytes() != crc {
+            return Err("PNG chunk checksum mismatch");
+        }
+        if is_first && (chunk_type != b"IHDR" || chunk_data.len() != 13) {
+            return Err("missing PNG IHDR chunk");
+        }
+        is_first = false;
+        chunks = &chunks[12 + length..];
+        if chunk_type == b"IEND" {
+            return match chunks.is_empty() {
+                true => Ok(()),
+                false => Err("data after PNG IEND chunk"),
+            };
+        }
+    }
+}
+
+/// CRC-32 checksum as defined in the PNG specification
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => 0xedb8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+        }
+    }
+    crc ^ 0xffff_ffff
+}
+
+/// Remove the images that are not referenced by a glif image element in any
+/// layer. Returns the sorted paths of the removed images.
+pub(crate) fn remove_unused_images(ufo: &mut Font, ufopath: &Path) -> Vec<PathBuf> {
+    let used_images: HashSet<&Path> = ufo
+        .layers
+        .iter()
+        .flat_map(|layer| layer.iter())
+        .filter_map(|glyph| glyph.image.as_ref().map(|image| image.file_name.as_path()))
+        .collect();
+    let mut unused_images: Vec<PathBuf> =
+        ufo.images.keys().filter(|p| !used_images.contains(p.as_path())).cloned().collect();
+    unused_images.sort();
+    for image_path in unused_images.iter() {
+        ufo.images.remove(image_path);
+    }
+    unused_images.into_iter().map(|p| ufopath.join("images").join(p)).collect()
+}
+
+/// Normalize CRLF and CR line endings to LF in data files with UTF-8 text.
+/// Returns the sorted paths of the modified data files.
+pub(crate) fn normalize_data_line_endings(ufo: &mut Font, ufopath: &Path) -> Result<Vec<PathBuf>> {
+    let mut normalized = Vec::new();
+    for (data_path, contents) in ufo.data.iter() {
+        let data = contents
+            .map_err(|e| Error::InvalidData(ufopath.join("data").join(data_path), e.to_string()))?;
+        if let Some(text) = get_text(&data) {
+            if text.contains('\r') {
+                let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                normalized.push((data_path.clone(), text.into_bytes()));
+            }
+        }
+    }
+    normalized.sort();
+    let mut normalized_paths = Vec::new();
+    for (data_path, data) in normalized {
+        let path = ufopath.join("data").join(&data_path);
+        ufo.data
+            .insert(data_path, data)
+            .map_err(|e| Error::InvalidData(path.clone(), e.to_string()))?;
+        normalized_paths.push(path);
+    }
+    Ok(normalized_paths)
+}
+
+/// Returns the data file contents as text when the data is UTF-8 without NUL bytes
+fn get_text(data: &[u8]) -> Option<&str> {
+    match data.contains(&0) {
+        true => None,
+        false => std::str::from_utf8(data).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use fs_extra::dir::{copy, CopyOptions};
+
+    /// 1x1 px grayscale PNG image
+    const PNG_IMAGE: &[u8] = &[
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 1, 0, 0, 0, 1, 8, 0,
+        0, 0, 0, 58, 126, 155, 85, 0, 0, 0, 10, 73, 68, 65, 84, 120, 156, 99, 96, 0, 0, 0, 2, 0, 1,
+        72, 175, 164, 113, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+    ];
+
+    fn get_test_ufo(tmp_dir: &tempdir::TempDir) -> PathBuf {
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        tmp_dir.path().join("MutatorSansBoldCondensed.ufo")
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn test_validate_png() {
+        assert_eq!(validate_png(PNG_IMAGE), Ok(()));
+        assert_eq!(validate_png(b"not a png"), Err("missing PNG signature"));
+        assert_eq!(validate_png(PNG_SIGNATURE), Err("truncated PNG chunk"));
+        assert_eq!(validate_png(&PNG_IMAGE[..PNG_IMAGE.len() - 4]), Err("truncated PNG chunk"));
+        let mut corrupt = PNG_IMAGE.to_vec();
+        corrupt[20] = 2;
+        assert_eq!(validate_png(&corrupt), Err("PNG chunk checksum mismatch"));
+        let trailing = [PNG_IMAGE, b"extra"].concat();
+        assert_eq!(validate_png(&trailing), Err("data after PNG IEND chunk"));
+        // the IEND chunk without the IHDR chunk
+        let no_ihdr = [PNG_SIGNATURE, &PNG_IMAGE[PNG_IMAGE.len() - 12..]].concat();
+        assert_eq!(validate_png(&no_ihdr), Err("missing PNG IHDR chunk"));
+    }
+
+    #[test]
+    fn test_validate_stores() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        let images_path = test_ufo_path.join("images");
+        fs::create_dir(&images_path).unwrap();
+        fs::write(images_path.join("a.png"), PNG_IMAGE).unwrap();
+        let ufo = Font::load(&test_ufo_path).unwrap();
+        assert!(validate_stores(&ufo, &test_ufo_path, true).is_ok());
+
+        // images with a PNG signature and invalid PNG chunks
+        fs::write(images_path.join("b.png"), &PNG_IMAGE[..20]).unwrap();
+        let ufo = Font::load(&test_ufo_path).unwrap();
+        assert!(validate_stores(&ufo, &test_ufo_path, false).is_ok());
+        let res = validate_stores(&ufo, &test_ufo_path, true);
+        assert!(matches!(res, Err(Error::InvalidImage(p, _)) if p == images_path.join("b.png")));
+
+        // images without a PNG signature are always invalid
+        fs::write(images_path.join("b.png"), b"not a png").unwrap();
+        let ufo = Font::load(&test_ufo_path).unwrap();
+        let res = validate_stores(&ufo, &test_ufo_path, false);
+        assert!(matches!(res, Err(Error::InvalidImage(p, _)) if p == images_path.join("b.png")));
+    }
+
+    #[test]
+    fn test_remove_unused_images() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        let images_path = test_ufo_path.join("images");
+        fs::create_dir(&images_path).unwrap();
+        for image in &["a.png", "b.png", "c.png"] {
+            fs::write(images_path.join(image), PNG_IMAGE).unwrap();
+        }
+        // reference a.png in the default layer and c.png in the background layer
+        for (glif_path, image) in
+            &[("glyphs/A_.glif", "a.png"), ("glyphs.background/S_.closed.glif", "c.png")]
+        {
+            let glif_path = test_ufo_path.join(glif_path);
+            let glif_str = fs::read_to_string(&glif_path).unwrap();
+            let image_element = format!("<image fileName=\"{}\"/>\n  <outline>", image);
+            fs::write(&glif_path, glif_str.replacen("<outline>", &image_element, 1)).unwrap();
+        }
+
+        let mut ufo = Font::load(&test_ufo_path).unwrap();
+        let removed = remove_unused_images(&mut ufo, &test_ufo_path);
+        assert_eq!(removed, vec![images_path.join("b.png")]);
+        let mut image_keys: Vec<&PathBuf> = ufo.images.keys().collect();
+        image_keys.sort();
+        assert_eq!(image_keys, vec![Path::new("a.png"), Path::new("c.png")]);
+        assert!(remove_unused_images(&mut ufo, &test_ufo_path).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_data_line_endings() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let test_ufo_path = get_test_ufo(&tmp_dir);
+        let data_path = test_ufo_path.join("data").join("com.example");
+        fs::create_dir_all(&data_path).unwrap();
+        fs::write(data_path.join("crlf.txt"), "one\r\ntwo\rthree\n").unwrap();
+        fs::write(data_path.join("lf.txt"), "one\ntwo\n").unwrap();
+        fs::write(data_path.join("binary.bin"), b"\x00\r\n\xff").unwrap();
+
+        let mut ufo = Font::load(&test_ufo_path).unwrap();
+        let normalized = normalize_data_line_endings(&mut ufo, &test_ufo_path).unwrap();
+        assert_eq!(normalized, vec![data_path.join("crlf.txt")]);
+        let get_data = |ufo: &Font, p: &str| ufo.data.get(Path::new(p)).unwrap().unwrap().to_vec();
+        assert_eq!(get_data(&ufo, "com.example/crlf.txt"), b"one\ntwo\nthree\n");
+        assert_eq!(get_data(&ufo, "com.example/lf.txt"), b"one\ntwo\n");
+        assert_eq!(get_data(&ufo, "com.example/binary.bin"), b"\x00\r\n\xff");
+    }
+}
@@ -1,11 +1,17 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ffi::OsStr;
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::lib::designspace;
+use crate::lib::errors::{Error, Result};
 
 pub(crate) fn get_ufo_outpath(
     user_ufo_path: &Path,
     user_unique_filename: &Option<String>,
     user_unique_extension: &Option<String>,
-) -> PathBuf {
+) -> Result<PathBuf> {
     let original_basepath = match user_ufo_path.parent() {
         Some(opar) => opar,
         None => Path::new("."),
@@ -15,7 +21,8 @@ pub(crate) fn get_ufo_outpath(
 
     let original_dir_rootpath = match user_ufo_path.file_stem() {
         Some(oroot) => oroot,
-        None => panic!("missing directory root!"),
+        // root paths and paths that end in `.` or `..` do not have a directory name
+        None => return Err(Error::InvalidUfoPath(user_ufo_path.into())),
     };
 
     let mut new_outpath = match user_unique_filename {
@@ -42,54 +49,368 @@ pub(crate) fn get_ufo_outpath(
         None => new_outpath.set_extension(original_extension),
     };
 
-    new_outpath
+    Ok(new_outpath)
+}
+
+/// Returns the UFO source path relative to the working directory, or None if
+/// the source is not located in the working directory
+pub(crate) fn get_ufo_relpath(user_ufo_path: &Path) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?.canonicalize().ok()?;
+    let abs_ufo_path = user_ufo_path.canonicalize().ok()?;
+    let relpath = abs_ufo_path.strip_prefix(cwd).ok()?;
+    match relpath.as_os_str().is_empty() {
+        true => None,
+        false => Some(relpath.to_path_buf()),
+    }
+}
+
+/// Returns the absolute path with symbolic links resolved in the existing
+/// components of `path`. Unlike `Path::canonicalize`, `path` does not need to exist.
+pub(crate) fn get_absolute_path(path: &Path) -> io::Result<PathBuf> {
+    let mut abs_path = std::env::current_dir()?;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => abs_path.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                abs_path.pop();
+            }
+            Component::Normal(name) => {
+                abs_path.push(name);
+                if abs_path.exists() {
+                    abs_path = abs_path.canonicalize()?;
+                }
+            }
+        }
+    }
+    Ok(abs_path)
+}
+
+/// Returns the sorted paths of all files in the `dir` directory tree relative to `dir`
+pub(crate) fn list_files(dir: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    fn visit(dir: &Path, reldir: &Path, files: &mut BTreeSet<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let relpath = reldir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                visit(&entry.path(), &relpath, files)?;
+            } else {
+                files.insert(relpath);
+            }
+        }
+        Ok(())
+    }
+    let mut files = BTreeSet::new();
+    visit(dir, Path::new(""), &mut files)?;
+    Ok(files)
+}
+
+/// The source files that a written directory tree is compared with
+pub(crate) enum SourceFiles<'a> {
+    /// A directory tree that is not modified by the write
+    Dir(&'a Path),
+    /// The file contents of a directory tree that the write replaces by relative
+    /// file path, without the contents of the files that are not compared
+    Contents(BTreeMap<PathBuf, Option<Vec<u8>>>),
+}
+
+impl SourceFiles<'_> {
+    /// Read the file contents of the `dir` directory tree.  Files for which
+    /// `skip_contents` returns true are listed without reading them.
+    pub(crate) fn read(
+        dir: &Path,
+        skip_contents: impl Fn(&Path) -> bool,
+    ) -> io::Result<SourceFiles<'static>> {
+        let mut contents = BTreeMap::new();
+        for relpath in list_files(dir)? {
+            let file_contents = match skip_contents(&relpath) {
+                true => None,
+                false => Some(fs::read(dir.join(&relpath))?),
+            };
+            contents.insert(relpath, file_contents);
+        }
+        Ok(SourceFiles::Contents(contents))
+    }
+
+    fn list_files(&self) -> io::Result<BTreeSet<PathBuf>> {
+        match self {
+            SourceFiles::Dir(dir) => list_files(dir),
+            SourceFiles::Contents(contents) => Ok(contents.keys().cloned().collect()),
+        }
+    }
+
+    /// Returns true when the `written` file differs from the source file at
+    /// `relpath`.  Files with different sizes are not read.
+    fn differs(&self, relpath: &Path, written: &Path) -> io::Result<bool> {
+        let written_len = fs::metadata(written)?.len();
+        match self {
+            SourceFiles::Dir(dir) => {
+                let source = dir.join(relpath);
+                Ok(fs::metadata(&source)?.len() != written_len
+                    || fs::read(&source)? != fs::read(written)?)
+            }
+            SourceFiles::Contents(contents) => match &contents[relpath] {
+                Some(source) => {
+                    Ok(source.len() as u64 != written_len || *source != fs::read(written)?)
+                }
+                None => Ok(false),
+            },
+        }
+    }
+}
+
+/// Returns the sorted relative paths of the files that differ between the source
+/// files and the `written` directory tree, including the files that are only in
+/// one of them.  The contents of files for which `skip_contents` returns true are
+/// not compared.
+pub(crate) fn get_changed_files(
+    source: &SourceFiles,
+    written: &Path,
+    skip_contents: impl Fn(&Path) -> bool,
+) -> io::Result<Vec<PathBuf>> {
+    let source_files = source.list_files()?;
+    let written_files = list_files(written)?;
+    let mut changed = Vec::new();
+    for relpath in source_files.union(&written_files) {
+        let is_changed = match source_files.contains(relpath) && written_files.contains(relpath) {
+            true => !skip_contents(relpath) && source.differs(relpath, &written.join(relpath))?,
+            false => true,
+        };
+        if is_changed {
+            changed.push(relpath.clone());
+        }
+    }
+    Ok(changed)
+}
+
+/// Returns the paths in a newline separated path list, or a NUL separated path
+/// list when `nul_separated` is true.  Empty entries are skipped, and CRLF line
+/// endings are supported in newline separated lists.  `source` is the path of
+/// the list that is used in errors.
+pub(crate) fn parse_path_list(
+    contents: &[u8],
+    nul_separated: bool,
+    source: &Path,
+) -> Result<Vec<PathBuf>> {
+    let separator = if nul_separated { b'\0' } else { b'\n' };
+    let mut paths = Vec::new();
+    for (index, mut entry) in contents.split(|b| *b == separator).enumerate() {
+        if !nul_separated && entry.ends_with(b"\r") {
+            entry = &entry[..entry.len() - 1];
+        }
+        if entry.is_empty() {
+            continue;
+        }
+        match path_from_bytes(entry) {
+            Some(path) => paths.push(path),
+            None => {
+                let msg = format!("entry {} is not a valid path", index + 1);
+                return Err(Error::InvalidPathList(source.into(), msg));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(PathBuf::from(OsStr::from_bytes(bytes)))
+}
+
+// paths on other platforms are not byte strings and must be UTF-8 in path lists
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> Option<PathBuf> {
+    std::str::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// 64-bit FNV-1a hash
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Copy the `src` file or directory to `dst`. Fails if `dst` exists.
+pub(crate) fn copy_path(src: &Path, dst: &Path) -> io::Result<()> {
+    if !src.is_file() {
+        return copy_dir_all(src, dst);
+    }
+    let mut dst_file = fs::OpenOptions::new().write(true).create_new(true).open(dst)?;
+    io::copy(&mut fs::File::open(src)?, &mut dst_file).map(|_| ())
+}
+
+/// Recursively copy the `src` directory to `dst`. Fails if `dst` exists.
+pub(crate) fn copy_dir_all(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns a file name for a user name with the UFO 3 specification user name to
+/// file name algorithm. `existing` holds the lowercase file names that are in use
+/// and is used to resolve case-insensitive file name clashes.
+/// See https://unifiedfontobject.org/versions/ufo3/conventions/#usernametofilename
+pub(crate) fn user_name_to_file_name(
+    user_name: &str,
+    existing: &HashSet<String>,
+    prefix: &str,
+    suffix: &str,
+) -> String {
+    const MAX_FILE_NAME_LEN: usize = 255;
+    const ILLEGAL_CHARS: &[char] = &['"', '*', '+', '/', ':', '<', '>', '?', '[', '\\', ']', '|'];
+    const RESERVED_FILE_NAMES: &[&str] = &[
+        "con", "prn", "aux", "clock$", "nul", "a:-z:", "com1", "lpt1", "lpt2", "lpt3", "com2",
+        "com3", "com4",
+    ];
+
+    let mut filtered: Vec<char> = Vec::with_capacity(user_name.len());
+    for (i, c) in user_name.chars().enumerate() {
+        match c {
+            // an initial period is replaced when there is no prefix
+            '.' if i == 0 && prefix.is_empty() => filtered.push('_'),
+            c if (c as u32) < 32 || (c as u32) == 0x7f || ILLEGAL_CHARS.contains(&c) => {
+                filtered.push('_')
+            }
+            // uppercase chars are followed by an underscore
+            c if c.to_lowercase().ne(std::iter::once(c)) => {
+                filtered.push(c);
+                filtered.push('_');
+            }
+            c => filtered.push(c),
+        }
+    }
+    let max_len = MAX_FILE_NAME_LEN.saturating_sub(prefix.chars().count() + suffix.chars().count());
+    filtered.truncate(max_len);
+    let filtered: String = filtered.into_iter().collect();
+
+    // reserved file names are prefixed with an underscore in each period-separated part
+    let name = filtered
+        .split('.')
+        .map(|part| match RESERVED_FILE_NAMES.contains(&part.to_lowercase().as_str()) {
+            true => format!("_{}", part),
+            false => part.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(".");
+
+    let full_name = format!("{}{}{}", prefix, name, suffix);
+    if !existing.contains(&full_name.to_lowercase()) {
+        return full_name;
+    }
+    // resolve clashes with a 15 digit counter
+    let name: String = name.chars().take(max_len.saturating_sub(15)).collect();
+    let mut counter: u64 = 1;
+    loop {
+        let full_name = format!("{}{}{:015}{}", prefix, name, counter, suffix);
+        if !existing.contains(&full_name.to_lowercase()) {
+            return full_name;
+        }
+        counter += 1;
+    }
+}
+
+/// UFO source file types that can be formatted individually
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UfoFileType {
+    /// glif file in any layer directory
+    Glif,
+    /// metainfo.plist, fontinfo.plist, lib.plist, groups.plist or kerning.plist
+    FontPlist,
+    /// layercontents.plist
+    LayerContents,
+    /// contents.plist in any layer directory
+    Contents,
+    /// layerinfo.plist in any layer directory
+    LayerInfo,
+    /// features.fea
+    Features,
+    /// designspace file, which is not part of a UFO source
+    Designspace,
+}
+
+/// Infer the UFO source file type from a file path. Returns None for
+/// unsupported file types.
+pub(crate) fn get_ufo_file_type(filepath: &Path) -> Option<UfoFileType> {
+    let file_name = filepath.file_name()?.to_str()?;
+    match file_name {
+        "metainfo.plist" | "fontinfo.plist" | "lib.plist" | "groups.plist" | "kerning.plist" => {
+            Some(UfoFileType::FontPlist)
+        }
+        "layercontents.plist" => Some(UfoFileType::LayerContents),
+        "contents.plist" => Some(UfoFileType::Contents),
+        "layerinfo.plist" => Some(UfoFileType::LayerInfo),
+        "features.fea" => Some(UfoFileType::Features),
+        _ if filepath.extension() == Some(OsStr::new("glif")) => Some(UfoFileType::Glif),
+        _ if designspace::is_designspace_path(filepath) => Some(UfoFileType::Designspace),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
+// the tests pass borrowed paths to generic path arguments
+#[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_get_ufo_outpath_default() {
-        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &None);
+        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &None).unwrap();
         assert_eq!(op, PathBuf::from("one/two/three.ufo"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_filename() {
-        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &Some("-new".to_string()), &None);
+        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &Some("-new".to_string()), &None)
+            .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three-new.ufo"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_extension_two_extensions_withperiod() {
         let op =
-            get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some(".fmt.ufo".to_string()));
+            get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some(".fmt.ufo".to_string()))
+                .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three.fmt.ufo"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_extension_two_extensions_without_period() {
         let op =
-            get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("fmt.ufo".to_string()));
+            get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("fmt.ufo".to_string()))
+                .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three.fmt.ufo"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_extension_single_extension_with_period() {
-        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some(".fmt".to_string()));
+        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some(".fmt".to_string()))
+            .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three.fmt"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_extension_single_extension_without_period() {
-        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("fmt".to_string()));
+        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("fmt".to_string()))
+            .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three.fmt"));
     }
 
     #[test]
     fn test_get_ufo_path_unique_extension_empty_extension() {
-        let op = get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("".to_string()));
+        let op =
+            get_ufo_outpath(&Path::new("one/two/three.ufo"), &None, &Some("".to_string())).unwrap();
         assert_eq!(op, PathBuf::from("one/two/three"));
     }
 
@@ -99,7 +420,271 @@ mod tests {
             &Path::new("one/two/three.ufo"),
             &Some("-new".to_string()),
             &Some("fmt".to_string()),
-        );
+        )
+        .unwrap();
         assert_eq!(op, PathBuf::from("one/two/three-new.fmt"));
     }
+
+    #[test]
+    fn test_get_ufo_outpath_trailing_slash() {
+        let op = get_ufo_outpath(Path::new("one/two/three.ufo/"), &Some("-new".to_string()), &None)
+            .unwrap();
+        assert_eq!(op, PathBuf::from("one/two/three-new.ufo"));
+    }
+
+    #[test]
+    fn test_get_ufo_outpath_parent_dir_component() {
+        let op = get_ufo_outpath(Path::new("one/../three.ufo"), &None, &Some("fmt".to_string()))
+            .unwrap();
+        assert_eq!(op, PathBuf::from("one/../three.fmt"));
+    }
+
+    #[test]
+    fn test_get_ufo_outpath_without_directory_name() {
+        for path in &["/", ".", "..", "one/two/..", "one/two/three.ufo/.."] {
+            let res = get_ufo_outpath(Path::new(path), &Some("-new".to_string()), &None);
+            assert!(matches!(res, Err(Error::InvalidUfoPath(p)) if p == Path::new(path)));
+        }
+    }
+
+    #[test]
+    fn test_user_name_to_file_name() {
+        // UFO 3 specification examples
+        let examples = [
+            ("a", "a"),
+            ("A", "A_"),
+            ("AE", "A_E_"),
+            ("Ae", "A_e"),
+            ("ae", "ae"),
+            ("aE", "aE_"),
+            ("a.alt", "a.alt"),
+            ("A.alt", "A_.alt"),
+            ("A.Alt", "A_.A_lt"),
+            ("A.aLt", "A_.aL_t"),
+            ("A.alT", "A_.alT_"),
+            ("T_H", "T__H_"),
+            ("T_h", "T__h"),
+            ("t_h", "t_h"),
+            ("F_F_I", "F__F__I_"),
+            ("f_f_i", "f_f_i"),
+            ("Aacute_V.swash", "A_acute_V_.swash"),
+            (".notdef", "_notdef"),
+            ("con", "_con"),
+            ("CON", "C_O_N_"),
+            ("con.alt", "_con.alt"),
+            ("alt.con", "alt._con"),
+        ];
+        for (user_name, file_name) in examples.iter() {
+            assert_eq!(
+                user_name_to_file_name(user_name, &HashSet::new(), "", ".glif"),
+                format!("{}.glif", file_name)
+            );
+        }
+        // illegal and control chars
+        assert_eq!(
+            user_name_to_file_name("a\"*+/:<>?[\\]|\u{7f}\u{1}", &HashSet::new(), "", ".glif"),
+            "a______________.glif"
+        );
+        // non-ASCII uppercase chars
+        assert_eq!(user_name_to_file_name("É", &HashSet::new(), "", ".glif"), "É_.glif");
+        // layer directory names
+        assert_eq!(
+            user_name_to_file_name(".background", &HashSet::new(), "glyphs.", ""),
+            "glyphs..background"
+        );
+    }
+
+    #[test]
+    fn test_user_name_to_file_name_clash() {
+        let mut existing = HashSet::new();
+        existing.insert("a.glif".to_string());
+        assert_eq!(user_name_to_file_name("a", &existing, "", ".glif"), "a000000000000001.glif");
+        existing.insert("a000000000000001.glif".to_string());
+        assert_eq!(user_name_to_file_name("a", &existing, "", ".glif"), "a000000000000002.glif");
+        // clashes are case-insensitive
+        let mut existing = HashSet::new();
+        existing.insert("a_.glif".to_string());
+        assert_eq!(user_name_to_file_name("A", &existing, "", ".glif"), "A_000000000000001.glif");
+    }
+
+    #[test]
+    fn test_user_name_to_file_name_max_length() {
+        let long_name = "a".repeat(300);
+        let file_name = user_name_to_file_name(&long_name, &HashSet::new(), "", ".glif");
+        assert_eq!(file_name.len(), 255);
+        let mut existing = HashSet::new();
+        existing.insert(file_name);
+        let file_name = user_name_to_file_name(&long_name, &existing, "", ".glif");
+        assert_eq!(file_name.len(), 255);
+        assert!(file_name.ends_with("000000000000001.glif"));
+    }
+
+    #[test]
+    fn test_get_ufo_file_type() {
+        assert_eq!(get_ufo_file_type(Path::new("glyphs/A_.glif")), Some(UfoFileType::Glif));
+        assert_eq!(
+            get_ufo_file_type(Path::new("Test.ufo/fontinfo.plist")),
+            Some(UfoFileType::FontPlist)
+        );
+        assert_eq!(get_ufo_file_type(Path::new("lib.plist")), Some(UfoFileType::FontPlist));
+        assert_eq!(
+            get_ufo_file_type(Path::new("layercontents.plist")),
+            Some(UfoFileType::LayerContents)
+        );
+        assert_eq!(
+            get_ufo_file_type(Path::new("glyphs.background/contents.plist")),
+            Some(UfoFileType::Contents)
+        );
+        assert_eq!(
+            get_ufo_file_type(Path::new("glyphs/layerinfo.plist")),
+            Some(UfoFileType::LayerInfo)
+        );
+        assert_eq!(get_ufo_file_type(Path::new("features.fea")), Some(UfoFileType::Features));
+        assert_eq!(
+            get_ufo_file_type(Path::new("Font.designspace")),
+            Some(UfoFileType::Designspace)
+        );
+    }
+
+    #[test]
+    fn test_get_ufo_file_type_unsupported() {
+        assert_eq!(get_ufo_file_type(Path::new("data/com.example/notes.plist")), None);
+        assert_eq!(get_ufo_file_type(Path::new("images/A.png")), None);
+        assert_eq!(get_ufo_file_type(Path::new("glif")), None);
+        assert_eq!(get_ufo_file_type(Path::new("")), None);
+    }
+
+    #[test]
+    fn test_get_ufo_relpath() {
+        assert_eq!(
+            get_ufo_relpath(Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo")),
+            Some(PathBuf::from("testdata/ufo/MutatorSansBoldCondensed.ufo"))
+        );
+        assert_eq!(
+            get_ufo_relpath(Path::new("testdata/ufo/../ufo/./MutatorSansBoldCondensed.ufo")),
+            Some(PathBuf::from("testdata/ufo/MutatorSansBoldCondensed.ufo"))
+        );
+        // the working directory itself and sources outside of it have no relative path
+        assert_eq!(get_ufo_relpath(Path::new(".")), None);
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        assert_eq!(get_ufo_relpath(tmp_dir.path()), None);
+        // sources must exist
+        assert_eq!(get_ufo_relpath(Path::new("totally/bogus/path/test.ufo")), None);
+    }
+
+    #[test]
+    fn test_get_absolute_path() {
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        assert_eq!(get_absolute_path(src_ufo_path).unwrap(), cwd.join(src_ufo_path));
+        assert_eq!(
+            get_absolute_path(Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo/../new.ufo"))
+                .unwrap(),
+            cwd.join("testdata/ufo/new.ufo")
+        );
+        assert_eq!(
+            get_absolute_path(Path::new("./bogus/dir/../test.ufo")).unwrap(),
+            cwd.join("bogus/test.ufo")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_absolute_path_symlink() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let target = tmp_dir.path().join("target");
+        let link = tmp_dir.path().join("link");
+        fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        assert_eq!(
+            get_absolute_path(&link.join("test.ufo")).unwrap(),
+            target.canonicalize().unwrap().join("test.ufo")
+        );
+    }
+
+    #[test]
+    fn test_list_files() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        fs::create_dir_all(tmp_dir.path().join("one/two")).unwrap();
+        fs::create_dir_all(tmp_dir.path().join("empty")).unwrap();
+        fs::write(tmp_dir.path().join("b.txt"), "").unwrap();
+        fs::write(tmp_dir.path().join("one/two/a.txt"), "").unwrap();
+        let files = list_files(tmp_dir.path()).unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<PathBuf>>(),
+            vec![PathBuf::from("b.txt"), PathBuf::from("one/two/a.txt")]
+        );
+        assert!(list_files(Path::new("totally/bogus/path")).is_err());
+    }
+
+    #[test]
+    fn test_get_changed_files() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let source_dir = tmp_dir.path().join("source");
+        fs::create_dir_all(source_dir.join("one")).unwrap();
+        fs::write(source_dir.join("a.txt"), "a").unwrap();
+        fs::write(source_dir.join("b.txt"), "b").unwrap();
+        fs::write(source_dir.join("one/c.txt"), "c").unwrap();
+        let skip_one = |relpath: &Path| relpath.starts_with("one");
+        let before = SourceFiles::read(&source_dir, skip_one).unwrap();
+        assert!(
+            matches!(&before, SourceFiles::Contents(c) if c.len() == 3 && c[Path::new("one/c.txt")].is_none())
+        );
+        assert!(get_changed_files(&before, &source_dir, skip_one).unwrap().is_empty());
+
+        // written directory trees are compared with the source directory tree
+        let written_dir = tmp_dir.path().join("written");
+        fs::create_dir_all(written_dir.join("one")).unwrap();
+        fs::write(written_dir.join("a.txt"), "A").unwrap();
+        fs::write(written_dir.join("b.txt"), "bb").unwrap();
+        // the contents of skipped files are not compared
+        fs::write(written_dir.join("one/c.txt"), "C").unwrap();
+        fs::write(written_dir.join("one/d.txt"), "d").unwrap();
+        let expected =
+            vec![PathBuf::from("a.txt"), PathBuf::from("b.txt"), PathBuf::from("one/d.txt")];
+        let source = SourceFiles::Dir(&source_dir);
+        assert_eq!(get_changed_files(&source, &written_dir, skip_one).unwrap(), expected);
+
+        // replaced directory trees are compared with the source file contents
+        fs::write(source_dir.join("a.txt"), "A").unwrap();
+        fs::remove_file(source_dir.join("b.txt")).unwrap();
+        fs::write(source_dir.join("one/c.txt"), "C").unwrap();
+        fs::write(source_dir.join("one/d.txt"), "d").unwrap();
+        assert_eq!(get_changed_files(&before, &source_dir, skip_one).unwrap(), expected);
+        assert!(SourceFiles::read(Path::new("totally/bogus/path"), |_| false).is_err());
+        assert!(get_changed_files(&source, Path::new("totally/bogus/path"), |_| false).is_err());
+    }
+
+    #[test]
+    fn test_parse_path_list() {
+        let source = Path::new("list.txt");
+        let res = parse_path_list(b"a.ufo\nMy Font.ufo\r\n\nb.ufo\n", false, source);
+        assert_eq!(
+            res.unwrap(),
+            vec![PathBuf::from("a.ufo"), PathBuf::from("My Font.ufo"), PathBuf::from("b.ufo")]
+        );
+        let res = parse_path_list(b"a.ufo\0line\nbreak.ufo\0", true, source);
+        assert_eq!(res.unwrap(), vec![PathBuf::from("a.ufo"), PathBuf::from("line\nbreak.ufo")]);
+        assert!(parse_path_list(b"", false, source).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_copy_dir_all() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let dst = tmp_dir.path().join("copy.ufo");
+        assert!(copy_dir_all(src, &dst).is_ok());
+        assert_eq!(
+            fs::read(src.join("glyphs").join("A_.glif")).unwrap(),
+            fs::read(dst.join("glyphs").join("A_.glif")).unwrap()
+        );
+        // existing destination directories are not overwritten
+        assert_eq!(copy_dir_all(src, &dst).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    }
 }
@@ -0,0 +1,393 @@
+//! Formatted UFO source roundtrip verification
+//!
+//! The written UFO source is loaded again and compared with the UFO source as
+//! it was loaded before formatting.  The glyph normalizations that were
+//! requested with the formatting options are applied to the loaded glyphs, so
+//! normalized contours, element order, and identifiers are compared.  Other
+//! changes that were requested with the formatting options are not compared,
+//! and verification errors name them.  The metainfo.plist file is not compared
+//! because norad writes its own creator and format version.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use norad::{Font, Glyph, Layer};
+
+use crate::lib::errors::{Error, Result};
+use crate::lib::formatters::FormatOptions;
+use crate::lib::glyphs::{self, GlyphNormalizations};
+use crate::lib::lenient;
+
+/// Number of differences that are described in verification errors
+const MAX_REPORTED_DIFFERENCES: usize = 3;
+
+/// Source changes requested with the formatting options.  The glyph
+/// normalizations are applied to the expected glyphs, and the other affected
+/// fields are not compared with the written UFO source.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Normalizations {
+    pub(crate) features: bool,
+    /// the features were converted to the requested line ending
+    pub(crate) feature_line_endings: bool,
+    pub(crate) glyphs: GlyphNormalizations,
+    pub(crate) data: bool,
+    pub(crate) images: bool,
+}
+
+impl Normalizations {
+    pub(crate) fn from_options(fmt_options: &FormatOptions) -> Self {
+        Normalizations {
+            features: fmt_options.format_features,
+            // the converted features are only known after the source is read
+            feature_line_endings: false,
+            glyphs: GlyphNormalizations::from_options(fmt_options),
+            data: fmt_options.normalize_data,
+            images: fmt_options.remove_unused_images,
+        }
+    }
+
+    /// Returns descriptions of the fields that are not compared
+    fn descriptions(&self) -> Vec<&'static str> {
+        let fields = [
+            (self.features, "features"),
+            (self.feature_line_endings && !self.features, "feature line endings"),
+            (self.data, "data files"),
+            (self.images, "images"),
+        ];
+        fields.iter().filter(|(skipped, _)| *skipped).map(|(_, desc)| *desc).collect()
+    }
+}
+
+/// Load the UFO source at `outpath` and compare it with `ufo`, the source as it
+/// was loaded before formatting.  Unparsable glif files are skipped when
+/// `lenient` is true.
+pub(crate) fn verify_ufo(
+    ufo: &Font,
+    outpath: &Path,
+    lenient: bool,
+    normalizations: &Normalizations,
+) -> Result<()> {
+    let written = lenient::load_ufo(outpath, lenient)?;
+    let differences = get_differences(ufo, &written.ufo, normalizations);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let mut msg = differences[..differences.len().min(MAX_REPORTED_DIFFERENCES)].join("; ");
+    if differences.len() > MAX_REPORTED_DIFFERENCES {
+        msg.push_str(&format!(
+            "; and {} more differences",
+            differences.len() - MAX_REPORTED_DIFFERENCES
+        ));
+    }
+    let skipped = normalizations.descriptions();
+    if !skipped.is_empty() {
+        msg.push_str(&format!(
+            " (not compared because of the formatting options: {})",
+            skipped.join(", ")
+        ));
+    }
+    Err(Error::VerificationFailed(outpath.into(), msg))
+}
+
+/// Returns descriptions of the structural differences between two UFO sources
+/// that are not explained by the `normalizations`
+pub(crate) fn get_differences(
+    expected: &Font,
+    actual: &Font,
+    normalizations: &Normalizations,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    let is_font_info_equal =
+        match (normalizations.glyphs.element_order, &expected.font_info.guidelines) {
+            (true, Some(guidelines)) => {
+                let mut font_info = expected.font_info.clone();
+                let mut sorted_guidelines = guidelines.clone();
+                glyphs::sort_guidelines(&mut sorted_guidelines);
+                font_info.guidelines = Some(sorted_guidelines);
+                font_info == actual.font_info
+            }
+            _ => expected.font_info == actual.font_info,
+        };
+    if !is_font_info_equal {
+        differences.push("fontinfo differs".to_string());
+    }
+    if expected.groups != actual.groups {
+        differences.push("groups differ".to_string());
+    }
+    if expected.kerning != actual.kerning {
+        differences.push("kerning differs".to_string());
+    }
+    if expected.lib != actual.lib {
+        differences.push("lib differs".to_string());
+    }
+    let skip_features = normalizations.features || normalizations.feature_line_endings;
+    if !skip_features && expected.features != actual.features {
+        differences.push("features differ".to_string());
+    }
+
+    let expected_layers: Vec<&str> = expected.layers.names().map(|n| n.as_ref()).collect();
+    let actual_layers: Vec<&str> = actual.layers.names().map(|n| n.as_ref()).collect();
+    if expected_layers != actual_layers {
+        differences.push(format!(
+            "layers differ: expected {:?}, found {:?}",
+            expected_layers, actual_layers
+        ));
+    }
+    for expected_layer in expected.layers.iter() {
+        if let Some(actual_layer) = actual.layers.get(expected_layer.name()) {
+            differences.extend(get_layer_differences(expected_layer, actual_layer, normalizations));
+        }
+    }
+
+    if !normalizations.data {
+        let data_paths = get_store_differences(
+            expected.data.iter().map(|(p, d)| (p, d.ok())),
+            |p| actual.data.get(p).and_then(|d| d.ok()),
+            actual.data.keys(),
+        );
+        differences.extend(data_paths.iter().map(|p| format!("data file {} differs", p.display())));
+    }
+    if !normalizations.images {
+        let image_paths = get_store_differences(
+            expected.images.iter().map(|(p, d)| (p, d.ok())),
+            |p| actual.images.get(p).and_then(|d| d.ok()),
+            actual.images.keys(),
+        );
+        differences.extend(image_paths.iter().map(|p| format!("image {} differs", p.display())));
+    }
+    differences
+}
+
+fn get_layer_differences(
+    expected: &Layer,
+    actual: &Layer,
+    normalizations: &Normalizations,
+) -> Vec<String> {
+    let layer_name = expected.name();
+    let mut differences = Vec::new();
+    if expected.color != actual.color {
+        differences.push(format!("layer {} color differs", layer_name));
+    }
+    if expected.lib != actual.lib {
+        differences.push(format!("layer {} lib differs", layer_name));
+    }
+    let glyph_names: BTreeSet<&str> =
+        expected.iter().chain(actual.iter()).map(|g| g.name.as_ref()).collect();
+    for glyph_name in glyph_names {
+        match (expected.get_glyph(glyph_name), actual.get_glyph(glyph_name)) {
+            (Some(expected_glyph), Some(actual_glyph)) => {
+                let fields = get_glyph_differences(expected_glyph, actual_glyph, normalizations);
+                if !fields.is_empty() {
+                    differences.push(format!(
+                        "glyph {} in layer {} differs: {}",
+                        glyph_name,
+                        layer_name,
+                        fields.join(", ")
+                    ));
+                }
+            }
+            (Some(_), None) => {
+                differences.push(format!("glyph {} in layer {} is missing", glyph_name, layer_name))
+            }
+            (None, _) => differences
+                .push(format!("glyph {} in layer {} is unexpected", glyph_name, layer_name)),
+        }
+    }
+    differences
+}
+
+/// Returns the names of the glyph fields that differ after the glyph
+/// normalizations are applied to the expected glyph
+fn get_glyph_differences(
+    expected: &Glyph,
+    actual: &Glyph,
+    normalizations: &Normalizations,
+) -> Vec<&'static str> {
+    let expected = normalize_glyph(expected, &normalizations.glyphs);
+    let fields = [
+        ("format", expected.format == actual.format),
+        ("width", expected.width == actual.width),
+        ("height", expected.height == actual.height),
+        ("unicodes", expected.codepoints == actual.codepoints),
+        ("note", expected.note == actual.note),
+        ("guidelines", expected.guidelines == actual.guidelines),
+        ("anchors", expected.anchors == actual.anchors),
+        ("components", expected.components == actual.components),
+        ("contours", expected.contours == actual.contours),
+        ("image", expected.image == actual.image),
+        ("lib", expected.lib == actual.lib),
+    ];
+    fields.iter().filter(|(_, is_equal)| !is_equal).map(|(name, _)| *name).collect()
+}
+
+/// Apply the glyph normalizations to a copy of `glyph`, so that the loaded glyph
+/// is compared with the written glyph as it was normalized
+fn normalize_glyph<'a>(glyph: &'a Glyph, normalizations: &GlyphNormalizations) -> Cow<'a, Glyph> {
+    if normalizations.is_empty() {
+        return Cow::Borrowed(glyph);
+    }
+    let mut glyph = glyph.clone();
+    normalizations.apply(&mut glyph);
+    Cow::Owned(glyph)
+}
+
+/// Returns the sorted paths of the store entries that are missing, unexpected,
+/// or have different contents
+fn get_store_differences<'a, T: AsRef<[u8]>>(
+    expected: impl Iterator<Item = (&'a PathBuf, Option<T>)>,
+    get_actual: impl Fn(&Path) -> Option<T>,
+    actual_paths: impl Iterator<Item = &'a PathBuf>,
+) -> BTreeSet<PathBuf> {
+    let mut differences = BTreeSet::new();
+    let mut expected_paths = BTreeSet::new();
+    for (path, contents) in expected {
+        expected_paths.insert(path.clone());
+        let actual_contents = get_actual(path);
+        let is_equal = match (&contents, &actual_contents) {
+            (Some(a), Some(b)) => a.as_ref() == b.as_ref(),
+            _ => false,
+        };
+        if !is_equal {
+            differences.insert(path.clone());
+        }
+    }
+    differences.extend(actual_paths.filter(|p| !expected_paths.contains(*p)).cloned());
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use fs_extra::dir::{copy, CopyOptions};
+    use norad::Anchor;
+
+    use crate::lib::glyphs::IdentifierMode;
+
+    fn get_test_ufo() -> Font {
+        Font::load(Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo")).unwrap()
+    }
+
+    #[test]
+    fn test_get_differences_equal() {
+        assert!(get_differences(&get_test_ufo(), &get_test_ufo(), &Normalizations::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_differences() {
+        let expected = get_test_ufo();
+        let mut actual = get_test_ufo();
+        actual.features.push_str("# comment\n");
+        actual.font_info.italic_angle = Some(10.0.into());
+        let glyph = actual.layers.default_layer_mut().get_glyph_mut("A").unwrap();
+        glyph.width += 1.0;
+        glyph.contours.pop();
+        actual.layers.default_layer_mut().remove_glyph("B");
+        assert_eq!(
+            get_differences(&expected, &actual, &Normalizations::default()),
+            vec![
+                "fontinfo differs",
+                "features differ",
+                "glyph A in layer foreground differs: width, contours",
+                "glyph B in layer foreground is missing",
+            ]
+        );
+    }
+
+    fn normalize_font(font: &mut Font, normalizations: &GlyphNormalizations) {
+        let layer_names: Vec<String> = font.layers.names().map(|n| n.to_string()).collect();
+        for layer_name in layer_names {
+            let layer = font.layers.get_mut(&layer_name).unwrap();
+            layer.iter_mut().for_each(|glyph| normalizations.apply(glyph));
+        }
+    }
+
+    #[test]
+    fn test_get_differences_normalizations() {
+        let mut expected = get_test_ufo();
+        let glyph = expected.layers.default_layer_mut().get_glyph_mut("A").unwrap();
+        for name in ["top", "bottom"] {
+            glyph.anchors.push(Anchor::new(0.0, 0.0, Some(name.into()), None, None, None));
+        }
+        glyph.contours[0].points.rotate_left(1);
+        let glyph_normalizations = GlyphNormalizations {
+            point_types: true,
+            duplicate_points: true,
+            start_points: true,
+            element_order: true,
+            identifiers: IdentifierMode::Add,
+        };
+        let mut actual = expected.clone();
+        actual.features.push_str("# comment\n");
+        normalize_font(&mut actual, &glyph_normalizations);
+        let all = Normalizations {
+            features: true,
+            feature_line_endings: true,
+            glyphs: glyph_normalizations,
+            data: true,
+            images: true,
+        };
+        assert!(get_differences(&expected, &actual, &all).is_empty());
+
+        // the normalized contours are compared
+        let glyph = actual.layers.default_layer_mut().get_glyph_mut("A").unwrap();
+        glyph.width += 1.0;
+        glyph.contours[0].points.rotate_left(1);
+        assert_eq!(
+            get_differences(&expected, &actual, &all),
+            vec!["glyph A in layer foreground differs: width, contours"]
+        );
+        // the normalizations that are not requested are reported
+        let start_points_only = Normalizations {
+            glyphs: GlyphNormalizations { start_points: true, ..Default::default() },
+            ..Default::default()
+        };
+        let mut actual = expected.clone();
+        normalize_font(&mut actual, &start_points_only.glyphs);
+        assert!(get_differences(&expected, &actual, &start_points_only).is_empty());
+        actual.layers.default_layer_mut().get_glyph_mut("A").unwrap().anchors.reverse();
+        assert_eq!(
+            get_differences(&expected, &actual, &start_points_only),
+            vec!["glyph A in layer foreground differs: anchors"]
+        );
+        let differences = get_differences(&expected, &actual, &Normalizations::default());
+        assert_eq!(differences[0], "glyph A in layer foreground differs: anchors, contours");
+    }
+
+    #[test]
+    fn test_verify_ufo() {
+        let tmp_dir = tempdir::TempDir::new("test").unwrap();
+        let src_ufo_path = Path::new("testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+        assert!(res_ufo_copy.is_ok());
+        let test_ufo_path = tmp_dir.path().join("MutatorSansBoldCondensed.ufo");
+        let ufo = Font::load(&test_ufo_path).unwrap();
+        assert!(verify_ufo(&ufo, &test_ufo_path, false, &Normalizations::default()).is_ok());
+
+        // changes made outside of norad are detected
+        let data_path = test_ufo_path.join("data");
+        fs::create_dir(&data_path).unwrap();
+        fs::write(data_path.join("a.txt"), "a").unwrap();
+        let kerning_path = test_ufo_path.join("kerning.plist");
+        fs::remove_file(&kerning_path).unwrap();
+        let res = verify_ufo(&ufo, &test_ufo_path, false, &Normalizations::default());
+        assert!(matches!(
+            res,
+            Err(Error::VerificationFailed(p, msg))
+                if p == test_ufo_path && msg == "kerning differs; data file a.txt differs"
+        ));
+
+        // the fields that are not compared are named
+        let normalizations = Normalizations { features: true, data: true, ..Default::default() };
+        let res = verify_ufo(&ufo, &test_ufo_path, false, &normalizations);
+        assert!(matches!(
+            res,
+            Err(Error::VerificationFailed(_, msg)) if msg
+                == "kerning differs (not compared because of the formatting options: features, data files)"
+        ));
+    }
+}
@@ -33,21 +33,97 @@
 //! ```
 //!
 //! Enter `ufofmt --help` to view help documentation with all available command line options.
+//!
+//! ## Exit codes
+//! - `0`: the UFO sources are formatted and no files were changed
+//! - `1`: files were changed, or with `--check` and `--dry-run`, files would be changed
+//! - `2`: a UFO source or file could not be read or written
+//! - `3`: invalid command line arguments or configuration
+
+#![allow(special_module_name)]
 
-use std::path::PathBuf;
-use std::time::Instant;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
-use rayon::prelude::*;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
 // ufofmt library modules
 pub mod lib;
 
+use crate::lib::backup;
+use crate::lib::config;
+use crate::lib::discovery;
 use crate::lib::errors;
 use crate::lib::formatters;
+use crate::lib::git;
+use crate::lib::glyphs::IdentifierMode;
+use crate::lib::lenient;
+use crate::lib::line_endings::LineEnding;
+use crate::lib::lossiness;
+use crate::lib::lsp;
+use crate::lib::reports::{self, OutputFormat};
+use crate::lib::utils;
+
+/// Exit code when no files are changed
+const EXIT_SUCCESS: i32 = 0;
+/// Exit code when files are changed, or would be changed in check and dry run modes
+const EXIT_CHANGES: i32 = 1;
+/// Exit code for read/write errors
+const EXIT_ERROR: i32 = 2;
+/// Exit code for invalid command line arguments and configuration
+const EXIT_USAGE: i32 = 3;
+
+/// Parallel execution strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParallelMode {
+    /// Format UFO sources in parallel with sequential glif reads/writes
+    Ufo,
+    /// Format UFO sources one at a time with parallel glif reads/writes
+    Glyph,
+}
+
+impl FromStr for ParallelMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ufo" => Ok(ParallelMode::Ufo),
+            "glyph" => Ok(ParallelMode::Glyph),
+            _ => Err(format!("unsupported parallel mode: {}", s)),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Run a Language Server Protocol server over stdio
+    #[structopt(name = "lsp")]
+    Lsp,
+    /// Restore the UFO sources that were backed up in the last --backup run
+    #[structopt(name = "restore")]
+    Restore {
+        /// Backup directory path
+        #[structopt(
+            name = "BACKUP_DIR",
+            long = "backup-dir",
+            help = "Directory with the backups [default: .ufofmt-backups]"
+        )]
+        backup_dir: Option<PathBuf>,
+    },
+}
 
 #[derive(StructOpt, Debug)]
 #[structopt(about = "A fast, flexible UFO source formatter.  Built with Norad.")]
+// UFO source paths that are similar to a subcommand name (e.g., lsp.ufo, or a
+// restore directory after --) are not rejected as misspelled subcommands.  The
+// UFO source path argument takes all other values, so no external subcommand
+// is parsed.
+#[structopt(setting = AppSettings::AllowExternalSubcommands)]
 struct Opt {
     /// Format XML declaration attributes with single quotes
     #[structopt(
@@ -62,11 +138,178 @@ struct Opt {
 
     #[structopt(
         long = "indent-number",
-        help = "Number of indentation char per indent level (valid range = 1 - 4)",
+        help = "Number of indentation char per indent level",
         default_value = "1"
     )]
     indent_number: u8,
 
+    /// Configuration file path
+    #[structopt(
+        name = "CONFIG",
+        long = "config",
+        help = "Configuration file path [default: ufofmt.toml in the working directory]"
+    )]
+    config: Option<PathBuf>,
+
+    /// Format the OpenType feature file
+    #[structopt(
+        long = "fea",
+        help = "Format features.fea and included feature files (experimental)"
+    )]
+    format_features: bool,
+
+    /// Rename glif files
+    #[structopt(
+        long = "rename-glifs",
+        help = "Rename glif files with the UFO 3 specification user name to file name algorithm"
+    )]
+    rename_glifs: bool,
+
+    /// Rotate closed contours to a canonical start point
+    #[structopt(
+        long = "normalize-start-points",
+        help = "Rotate closed contours so that the on-curve point with the lowest y and then the lowest x coordinate is the start point"
+    )]
+    normalize_start_points: bool,
+
+    /// Remove duplicate consecutive on-curve points
+    #[structopt(
+        long = "remove-duplicate-points",
+        help = "Remove on-curve points at the same position as the previous on-curve point"
+    )]
+    remove_duplicate_points: bool,
+
+    /// Normalize implied point types
+    #[structopt(
+        long = "normalize-point-types",
+        help = "Set the type of curve and qcurve points that are not preceded by offcurve points to line"
+    )]
+    normalize_point_types: bool,
+
+    /// Glyph object identifier handling
+    #[structopt(
+        long = "identifiers",
+        name = "IDENTIFIER_MODE",
+        help = "Add deterministic identifiers to glyph objects without one (add), remove identifiers that are not referenced (strip), or write identifiers without changes (keep)",
+        possible_values = &["add", "strip", "keep"],
+        default_value = "keep"
+    )]
+    identifiers: IdentifierMode,
+
+    /// Line ending policy
+    #[structopt(
+        long = "line-ending",
+        name = "LINE_ENDING",
+        help = "Write glif, plist, and feature files with LF (lf) or CRLF (crlf) line endings, or keep the dominant line ending of each source file (preserve)",
+        possible_values = &["lf", "crlf", "preserve"],
+        default_value = "lf"
+    )]
+    line_ending: LineEnding,
+
+    /// Sort glif and fontinfo elements
+    #[structopt(
+        long = "sort-elements",
+        help = "Sort glyph unicode values after the primary value, anchors by name, and glyph and fontinfo guidelines by position"
+    )]
+    sort_elements: bool,
+
+    /// Verify the written UFO sources
+    #[structopt(
+        long = "verify",
+        help = "Load each written UFO source and fail when it differs from the formatted source"
+    )]
+    verify: bool,
+
+    /// Fail on discarded contents
+    #[structopt(
+        long = "strict",
+        help = "Fail instead of warning when formatting would discard source contents such as XML comments"
+    )]
+    strict: bool,
+
+    /// Skip unparsable glif files
+    #[structopt(
+        long = "lenient",
+        help = "Skip glif files that cannot be parsed, write them without changes, and format the rest of the UFO source"
+    )]
+    lenient: bool,
+
+    /// Validate images
+    #[structopt(long = "verify-images", help = "Validate the PNG file structure of images")]
+    verify_images: bool,
+
+    /// Remove images that are not used
+    #[structopt(
+        long = "remove-unused-images",
+        help = "Remove images that are not referenced by a glif image element in any layer"
+    )]
+    remove_unused_images: bool,
+
+    /// Normalize data file line endings
+    #[structopt(
+        long = "normalize-data",
+        help = "Normalize CRLF and CR line endings to LF in UTF-8 text files in the data directory"
+    )]
+    normalize_data: bool,
+
+    /// Remove files that are not written
+    #[structopt(
+        long = "clean",
+        help = "List the UFO source files that are removed because norad does not write them (e.g., glif files that are not in contents.plist, layer directories that are not in layercontents.plist, .DS_Store files)"
+    )]
+    clean: bool,
+
+    /// List the files that --clean removes without formatting
+    #[structopt(
+        long = "dry-run",
+        requires = "clean",
+        help = "List the files that --clean removes without formatting the UFO sources"
+    )]
+    dry_run: bool,
+
+    /// Report the UFO sources that would be changed without formatting
+    #[structopt(
+        long = "check",
+        conflicts_with = "clean",
+        help = "List the UFO source files that formatting would change without writing them. Exits with 1 when files would be changed"
+    )]
+    check: bool,
+
+    /// Number of worker threads
+    #[structopt(
+        short = "j",
+        long = "jobs",
+        help = "Number of worker threads [default: number of logical CPUs]"
+    )]
+    jobs: Option<usize>,
+
+    /// Parallel execution strategy
+    #[structopt(
+        long = "parallel",
+        help = "Parallelize across UFO sources (ufo) or across glyphs within each UFO source (glyph)",
+        possible_values = &["ufo", "glyph"],
+        default_value = "ufo"
+    )]
+    parallel: ParallelMode,
+
+    /// Formatting result output format
+    #[structopt(
+        long = "format",
+        name = "OUTPUT_FORMAT",
+        help = "Write the formatting results as status lines (text), a SARIF 2.1.0 log (sarif), or GitHub Actions annotations (github)",
+        possible_values = &["text", "sarif", "github"],
+        default_value = "text"
+    )]
+    output_format: OutputFormat,
+
+    /// JUnit XML report path
+    #[structopt(
+        name = "JUNIT_PATH",
+        long = "junit",
+        help = "Write a JUnit XML report with a test case for each UFO source, or for each UFO source file with --check and --dry-run"
+    )]
+    junit: Option<PathBuf>,
+
     /// Display timing data
     #[structopt(short = "t", long = "time", help = "Display timing data")]
     time: bool,
@@ -87,63 +330,557 @@ struct Opt {
     )]
     uniquename: Option<String>,
 
+    /// Output directory path
+    #[structopt(
+        name = "OUT_DIR",
+        long = "out-dir",
+        help = "Write formatted UFO sources to this directory. Source paths relative to the working directory are mirrored"
+    )]
+    out_dir: Option<PathBuf>,
+
+    /// Back up UFO sources before in-place formatting
+    #[structopt(long = "backup", help = "Back up UFO sources before in-place formatting")]
+    backup: bool,
+
+    /// Backup directory path
+    #[structopt(
+        name = "BACKUP_DIR",
+        long = "backup-dir",
+        help = "Write backups to this directory (implies --backup) [default: .ufofmt-backups]"
+    )]
+    backup_dir: Option<PathBuf>,
+
+    /// Format stdin contents as the UFO source file at this path
+    #[structopt(
+        name = "STDIN_FILEPATH",
+        long = "stdin-filepath",
+        help = "Format glif, plist, or feature file contents read from stdin and write to stdout. The file type is inferred from this path"
+    )]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Read UFO source paths from a file
+    #[structopt(
+        name = "FILES_FROM",
+        long = "files-from",
+        help = "Read newline separated UFO source paths from this file, or from stdin with -. The paths are formatted with the command line paths"
+    )]
+    files_from: Option<PathBuf>,
+
+    /// UFO source include patterns
+    #[structopt(
+        name = "INCLUDE_PATTERN",
+        long = "include",
+        number_of_values = 1,
+        help = "Only format UFO sources with paths relative to the working directory that match this glob pattern (e.g., 'masters/*.ufo'). Can be repeated"
+    )]
+    include: Vec<String>,
+
+    /// UFO source exclude patterns
+    #[structopt(
+        name = "EXCLUDE_PATTERN",
+        long = "exclude",
+        number_of_values = 1,
+        help = "Do not format UFO sources with paths relative to the working directory that match this glob pattern (e.g., '**/archive/**'). Can be repeated"
+    )]
+    exclude: Vec<String>,
+
+    /// Only format UFO sources that changed since a git revision
+    #[structopt(
+        name = "REVISION",
+        long = "since",
+        help = "Only format UFO sources with committed, uncommitted, or untracked file changes since this git revision. Searches the working directory when UFO source paths are not defined"
+    )]
+    since: Option<String>,
+
+    /// List the UFO sources without formatting
+    #[structopt(
+        long = "list-files",
+        help = "List the UFO source paths that would be formatted after directory searches and pattern filters without formatting"
+    )]
+    list_files: bool,
+
+    /// NUL separated path lists
+    #[structopt(
+        short = "0",
+        long = "null",
+        requires = "FILES_FROM",
+        help = "Read NUL separated paths with --files-from (e.g., from find -print0 or git ls-files -z)"
+    )]
+    null_separated: bool,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+
     /// UFO source file paths
-    #[structopt(help = "UFO source path(s)")]
+    #[structopt(
+        help = "UFO source path(s).  Enter -- before the paths to format UFO sources named like a subcommand (e.g., ufofmt -- restore)"
+    )]
     ufopaths: Vec<PathBuf>,
 }
 
 fn main() {
-    let argv = Opt::from_args();
+    let argv = match Opt::from_args_safe() {
+        Ok(argv) => argv,
+        // help and version requests are written to stdout with a success exit code
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            eprintln!("{}", e.message);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
 
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
     // CL arg validation checks
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
-    if argv.indent_number > 4 || argv.indent_number < 1 {
+    if argv.indent_number < 1 {
+        eprintln!(
+            "{} indentation char number must be a value greater than 0",
+            *errors::ERROR_INDICATOR,
+        );
+        std::process::exit(EXIT_USAGE);
+    }
+
+    if argv.jobs == Some(0) {
+        eprintln!("{} number of jobs must be a value greater than 0", *errors::ERROR_INDICATOR);
+        std::process::exit(EXIT_USAGE);
+    }
+
+    let config = match config::get_config(&argv.config) {
+        Ok(c) => c,
+        Err(err) => {
+            eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    // command line patterns are combined with the configuration file patterns
+    let include = [config.files.include.as_slice(), argv.include.as_slice()].concat();
+    let exclude = [config.files.exclude.as_slice(), argv.exclude.as_slice()].concat();
+    let path_filter = match discovery::PathFilter::new(&include, &exclude) {
+        Ok(f) => f,
+        Err(err) => {
+            eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    let fmt_options = formatters::FormatOptions {
+        singlequotes: argv.singlequotes,
+        indent_with_space: argv.indent_with_space,
+        indent_number: argv.indent_number,
+        glif_indent: config.glif.indent,
+        plist_indent: config.plist.indent,
+        designspace_indent: config.designspace.indent,
+        format_features: argv.format_features,
+        rename_glifs: argv.rename_glifs,
+        verify_images: argv.verify_images,
+        remove_unused_images: argv.remove_unused_images,
+        normalize_data: argv.normalize_data,
+        normalize_start_points: argv.normalize_start_points,
+        remove_duplicate_points: argv.remove_duplicate_points,
+        normalize_point_types: argv.normalize_point_types,
+        identifiers: argv.identifiers,
+        sort_elements: argv.sort_elements,
+        verify: argv.verify,
+        strict: argv.strict,
+        lenient: argv.lenient,
+        line_ending: argv.line_ending,
+    };
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Language server
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // The language server exit code is defined by the protocol exit notification
+    if let Some(Command::Lsp) = argv.cmd {
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+        match lsp::run(&mut stdin.lock(), &mut stdout.lock(), fmt_options) {
+            Ok(exit_code) => std::process::exit(exit_code),
+            Err(e) => {
+                eprintln!("{} language server I/O error: {}", *errors::ERROR_INDICATOR, e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Backup restore
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    if let Some(Command::Restore { backup_dir }) = &argv.cmd {
+        let manifest_path = backup::get_manifest_path(backup_dir);
+        let backups = match backup::read_manifest(&manifest_path) {
+            Ok(b) => b,
+            Err(err) => {
+                eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+                std::process::exit(EXIT_ERROR);
+            }
+        };
+        let results: Vec<errors::Result<PathBuf>> =
+            backups.iter().map(backup::restore_ufo).collect();
+        print_results(&results);
+        if results.iter().any(|v| v.is_err()) {
+            std::process::exit(EXIT_ERROR);
+        }
+        // the manifest is consumed so that a repeated restore does not
+        // overwrite changes that were made after the restore
+        if let Err(e) = std::fs::remove_file(&manifest_path) {
+            eprintln!("{} {}: {}", *errors::ERROR_INDICATOR, manifest_path.display(), e);
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+
+    if argv.stdin_filepath.is_some() && (!argv.ufopaths.is_empty() || argv.files_from.is_some()) {
         eprintln!(
-            "{} indentation char number must be a value between 1 - 4",
+            "{} UFO source paths and --files-from are not supported with the --stdin-filepath option",
             *errors::ERROR_INDICATOR,
         );
-        std::process::exit(1);
+        std::process::exit(EXIT_USAGE);
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Single file stdin/stdout formatting
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Formatted contents are written with a success exit code so that editor
+    // integrations accept them. Check mode writes nothing and reports changes.
+    if let Some(filepath) = &argv.stdin_filepath {
+        let mut contents = Vec::new();
+        if let Err(e) = std::io::stdin().read_to_end(&mut contents) {
+            eprintln!("{} unable to read stdin: {}", *errors::ERROR_INDICATOR, e);
+            std::process::exit(EXIT_ERROR);
+        }
+        match formatters::format_file_contents(filepath, &contents, &fmt_options) {
+            Ok(formatted) if argv.check => {
+                if formatted != contents {
+                    println!("{} would change: {}", *errors::DRY_RUN_INDICATOR, filepath.display());
+                    std::process::exit(EXIT_CHANGES);
+                }
+            }
+            Ok(formatted) => {
+                if let Err(e) = std::io::stdout().write_all(&formatted) {
+                    eprintln!("{} unable to write stdout: {}", *errors::ERROR_INDICATOR, e);
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
+            Err(err) => {
+                eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+        std::process::exit(EXIT_SUCCESS);
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Thread pool configuration
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // The global pool is shared with the norad library.  It runs the glif
+    // level parallelism and sizes the UFO level worker count.
+    if let Some(jobs) = argv.jobs {
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global() {
+            eprintln!("{} unable to configure thread pool: {}", *errors::ERROR_INDICATOR, e);
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Path list reads
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Path lists avoid command line length limits and shell quoting of paths
+    let mut user_ufopaths = argv.ufopaths.clone();
+    if let Some(files_from) = &argv.files_from {
+        let contents = match files_from.to_str() {
+            Some("-") => {
+                let mut contents = Vec::new();
+                std::io::stdin().read_to_end(&mut contents).map(|_| contents)
+            }
+            _ => std::fs::read(files_from),
+        };
+        let res_paths = contents
+            .map_err(|e| errors::Error::Io(files_from.clone(), e))
+            .and_then(|c| utils::parse_path_list(&c, argv.null_separated, files_from));
+        match res_paths {
+            Ok(paths) => user_ufopaths.extend(paths),
+            Err(err) => {
+                eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // UFO source discovery
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Directories are searched for UFO sources and the include and exclude
+    // patterns apply to all UFO source paths
+    if argv.since.is_some() && user_ufopaths.is_empty() {
+        user_ufopaths.push(PathBuf::from("."));
+    }
+    let mut user_ufopaths = match discovery::find_ufo_sources(&user_ufopaths, &path_filter) {
+        Ok(ufopaths) => ufopaths,
+        Err(err) => {
+            eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+
+    // UFO sources without changes since the revision are not formatted
+    if let Some(rev) = &argv.since {
+        let changed_ufos = match git::get_changed_ufos(Path::new("."), rev) {
+            Ok(changed_ufos) => changed_ufos,
+            Err(err) => {
+                eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+                std::process::exit(EXIT_ERROR);
+            }
+        };
+        user_ufopaths
+            .retain(|p| p.canonicalize().is_ok_and(|source| changed_ufos.contains(&source)));
+    }
+
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Output path collision checks
+    // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    // Duplicate UFO sources are formatted once and distinct UFO sources must
+    // not be written to the same output path in the parallel run
+    let ufopaths = match formatters::get_batch_ufopaths(
+        &user_ufopaths,
+        &argv.uniquename,
+        &argv.uniqueext,
+        &argv.out_dir,
+    ) {
+        Ok(ufopaths) => ufopaths,
+        Err(err) => {
+            eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    if argv.list_files {
+        for ufopath in &ufopaths {
+            println!("{}", ufopath.display());
+        }
+        std::process::exit(EXIT_SUCCESS);
     }
 
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
     // Source formatting execution
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
-    let now = Instant::now();
-    let results: Vec<errors::Result<PathBuf>> = argv
-        .ufopaths
-        .par_iter()
-        .map(|ufopath| {
-            formatters::format_ufo(
+    // backups are only needed when UFO sources are formatted in place
+    let backup_enabled = (argv.backup || argv.backup_dir.is_some())
+        && !argv.dry_run
+        && !argv.check
+        && argv.uniquename.is_none()
+        && argv.uniqueext.is_none()
+        && argv.out_dir.is_none();
+    let timestamp = backup::get_timestamp_str(SystemTime::now());
+    let backups = Mutex::new(Vec::new());
+
+    let format = |ufopath: &PathBuf| {
+        if backup_enabled {
+            let b = backup::backup_ufo(ufopath, &argv.backup_dir, &timestamp)?;
+            backups.lock().unwrap().push(b);
+        }
+        match (argv.clean, argv.dry_run) {
+            (true, true) => formatters::check_ufo(ufopath, &fmt_options),
+            _ if argv.check => formatters::check_ufo(ufopath, &fmt_options),
+            (true, false) => formatters::format_ufo_clean(
                 ufopath,
                 &argv.uniquename,
                 &argv.uniqueext,
-                argv.singlequotes,
-                argv.indent_with_space,
-                argv.indent_number,
-            )
-        })
-        .collect();
+                &argv.out_dir,
+                &fmt_options,
+            ),
+            _ => formatters::format_ufo(
+                ufopath,
+                &argv.uniquename,
+                &argv.uniqueext,
+                &argv.out_dir,
+                &fmt_options,
+            ),
+        }
+    };
+
+    // per UFO source durations are reported in JUnit test cases
+    let timed_format = |ufopath: &PathBuf| {
+        let now = Instant::now();
+        let result = format(ufopath);
+        (result, now.elapsed())
+    };
+
+    let now = Instant::now();
+    let (results, durations): (Vec<errors::Result<formatters::FormatSummary>>, Vec<Duration>) =
+        match argv.parallel {
+            ParallelMode::Ufo => match map_sequential_workers(&ufopaths, timed_format) {
+                Ok(timed_results) => timed_results.into_iter().unzip(),
+                Err(e) => {
+                    eprintln!(
+                        "{} unable to configure thread pool: {}",
+                        *errors::ERROR_INDICATOR,
+                        e
+                    );
+                    std::process::exit(EXIT_ERROR);
+                }
+            },
+            ParallelMode::Glyph => ufopaths.iter().map(timed_format).unzip(),
+        };
     let duration = now.elapsed().as_millis();
 
-    for result in &results {
+    // files are only checked and not written with the --check and --dry-run options
+    let is_check = argv.check || argv.dry_run;
+    match argv.output_format {
+        OutputFormat::Text => print_summaries(&results, argv.dry_run, argv.check),
+        OutputFormat::Sarif => {
+            println!("{}", reports::format_sarif(&reports::get_findings(&results, is_check)))
+        }
+        OutputFormat::Github => {
+            print!("{}", reports::format_github(&reports::get_findings(&results, is_check)))
+        }
+    }
+
+    if let Some(junit_path) = &argv.junit {
+        let junit = reports::format_junit(&ufopaths, &results, &durations, is_check);
+        if let Err(e) = std::fs::write(junit_path, junit) {
+            eprintln!("{} {}: {}", *errors::ERROR_INDICATOR, junit_path.display(), e);
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+
+    let backups = backups.into_inner().unwrap();
+    if !backups.is_empty() {
+        let manifest_path = backup::get_manifest_path(&argv.backup_dir);
+        if let Err(err) = backup::write_manifest(&manifest_path, &timestamp, &backups) {
+            eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+
+    // timing data is not mixed with the machine-readable results on stdout
+    if argv.time {
+        match argv.output_format {
+            OutputFormat::Text => println!("Total duration: {} ms", duration),
+            _ => eprintln!("Total duration: {} ms", duration),
+        }
+    }
+
+    // errors take precedence over changes in the exit code
+    if results.iter().any(|v| v.is_err()) {
+        std::process::exit(EXIT_ERROR);
+    }
+    if results.iter().flatten().any(|summary| !summary.changed.is_empty()) {
+        std::process::exit(EXIT_CHANGES);
+    }
+    std::process::exit(EXIT_SUCCESS);
+}
+
+/// Map `op` over the UFO source paths across the worker threads.  Each worker
+/// runs in a single thread pool so that the norad glif reads and writes within
+/// a UFO source are sequential.  Results are in the order of `ufopaths`.
+fn map_sequential_workers<T: Send>(
+    ufopaths: &[PathBuf],
+    op: impl Fn(&PathBuf) -> T + Sync,
+) -> Result<Vec<T>, rayon::ThreadPoolBuildError> {
+    let worker_count = rayon::current_num_threads().min(ufopaths.len());
+    let pools = (0..worker_count)
+        .map(|_| rayon::ThreadPoolBuilder::new().num_threads(1).build())
+        .collect::<Result<Vec<_>, _>>()?;
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(ufopaths.len()));
+    let (op, next_index_ref, results_ref) = (&op, &next_index, &results);
+    std::thread::scope(|scope| {
+        for pool in pools {
+            scope.spawn(move || {
+                pool.install(|| loop {
+                    let index = next_index_ref.fetch_add(1, Ordering::SeqCst);
+                    match ufopaths.get(index) {
+                        Some(ufopath) => {
+                            let result = op(ufopath);
+                            results_ref.lock().unwrap().push((index, result));
+                        }
+                        None => break,
+                    }
+                })
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+fn print_summaries(
+    results: &[errors::Result<formatters::FormatSummary>],
+    dry_run: bool,
+    check: bool,
+) {
+    for result in results {
         match result {
-            Ok(path) => {
-                println!("{} {}", *errors::OK_INDICATOR, path.display());
+            Ok(summary) if dry_run || check => {
+                println!("{} {}", *errors::DRY_RUN_INDICATOR, summary.source.display());
+                if dry_run {
+                    for path in &summary.unwritten {
+                        println!("    would remove: {}", path.display());
+                    }
+                }
+                if check {
+                    for path in &summary.changed {
+                        println!("    would change: {}", path.display());
+                    }
+                }
+                print_losses(&summary.losses);
+                print_skipped_glifs(&summary.skipped_glifs);
+            }
+            Ok(summary) => {
+                println!("{} {}", *errors::OK_INDICATOR, summary.outpath.display());
+                for path in &summary.removed_images {
+                    println!("    removed unused image: {}", path.display());
+                }
+                for path in &summary.normalized_data {
+                    println!("    normalized line endings: {}", path.display());
+                }
+                // files are only removed from UFO sources that are formatted in place
+                let label = match summary.outpath == summary.source {
+                    true => "removed",
+                    false => "not written",
+                };
+                for path in summary.unwritten.iter().filter(|p| !summary.removed_images.contains(p))
+                {
+                    println!("    {}: {}", label, path.display());
+                }
+                print_losses(&summary.losses);
+                print_skipped_glifs(&summary.skipped_glifs);
             }
             Err(err) => {
                 eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
             }
         }
     }
+}
 
-    if argv.time {
-        println!("Total duration: {} ms", duration);
+fn print_skipped_glifs(skipped_glifs: &[lenient::SkippedGlif]) {
+    for glif in skipped_glifs {
+        eprintln!(
+            "    {} skipped glyph {}: {}",
+            *errors::WARNING_INDICATOR,
+            glif.glyph_name,
+            glif.error
+        );
     }
+}
 
-    // An error was identified if any process returned a u8 value of 1
-    // If there was no error, the sum = 0
-    if results.iter().any(|v| v.is_err()) {
-        std::process::exit(1);
+fn print_losses(losses: &[lossiness::Loss]) {
+    for loss in losses {
+        eprintln!("    {} discarded {}", *errors::WARNING_INDICATOR, loss);
+    }
+}
+
+fn print_results(results: &[errors::Result<PathBuf>]) {
+    for result in results {
+        match result {
+            Ok(path) => {
+                println!("{} {}", *errors::OK_INDICATOR, path.display());
+            }
+            Err(err) => {
+                eprintln!("{} {}", *errors::ERROR_INDICATOR, err);
+            }
+        }
     }
 }
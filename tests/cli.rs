@@ -0,0 +1,530 @@
+//! Command line tests that run the ufofmt executable
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use fs_extra::dir::{copy, CopyOptions};
+use tempdir::TempDir;
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_CHANGES: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+const EXIT_USAGE: i32 = 3;
+
+fn ufofmt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ufofmt")).args(args).output().unwrap()
+}
+
+fn ufofmt_in(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ufofmt")).args(args).current_dir(dir).output().unwrap()
+}
+
+fn ufofmt_stdin(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ufofmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn get_test_ufo(tmp_dir: &TempDir, src_ufo_path: &str) -> PathBuf {
+    let res_ufo_copy = copy(src_ufo_path, tmp_dir.path(), &CopyOptions::new());
+    assert!(res_ufo_copy.is_ok());
+    tmp_dir.path().join(Path::new(src_ufo_path).file_name().unwrap())
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn test_exit_code_help() {
+    assert_eq!(ufofmt(&["--help"]).status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(ufofmt(&["--version"]).status.code(), Some(EXIT_SUCCESS));
+}
+
+#[test]
+fn test_exit_code_usage_errors() {
+    assert_eq!(ufofmt(&["--bogus-option"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(ufofmt(&["--dry-run", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(ufofmt(&["--jobs", "0", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(ufofmt(&["--indent-number", "0", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(
+        ufofmt(&["--config", "totally/bogus/ufofmt.toml", "test.ufo"]).status.code(),
+        Some(EXIT_USAGE)
+    );
+    assert_eq!(
+        ufofmt(&["--stdin-filepath", "glyphs/A_.glif", "test.ufo"]).status.code(),
+        Some(EXIT_USAGE)
+    );
+}
+
+#[test]
+fn test_exit_code_format() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    // the unformatted UFO source is changed
+    assert_eq!(ufofmt(&[path_str(&test_ufo_path)]).status.code(), Some(EXIT_CHANGES));
+    // the formatted UFO source is not changed
+    assert_eq!(ufofmt(&[path_str(&test_ufo_path)]).status.code(), Some(EXIT_SUCCESS));
+}
+
+#[test]
+fn test_jobs_and_parallel_modes() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let expected_ufo_path =
+        get_test_ufo(&tmp_dir, "testdata/expected/MutatorSansBoldCondensed-default.ufo");
+    let expected_glif = fs::read_to_string(expected_ufo_path.join("glyphs/A_.glif")).unwrap();
+    for (index, mode) in ["ufo", "glyph"].iter().enumerate() {
+        let mode_dir = TempDir::new("test").unwrap();
+        let ufo_one = get_test_ufo(&mode_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let ufo_two = mode_dir.path().join("Copy.ufo");
+        fs::rename(&ufo_one, &ufo_two).unwrap();
+        let ufo_one = get_test_ufo(&mode_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+        let jobs = (index + 1).to_string();
+        let output =
+            ufofmt(&["--jobs", &jobs, "--parallel", mode, path_str(&ufo_one), path_str(&ufo_two)]);
+        assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+        for ufopath in &[&ufo_one, &ufo_two] {
+            let glif = fs::read_to_string(ufopath.join("glyphs/A_.glif")).unwrap();
+            assert_eq!(glif, expected_glif);
+        }
+    }
+
+    assert_eq!(ufofmt(&["--parallel", "font", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(ufofmt(&["--jobs", "many", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    assert_eq!(ufofmt(&["--jobs", "-1", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+}
+
+#[test]
+fn test_exit_code_check() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+    let glif_str = fs::read_to_string(&glif_path).unwrap();
+
+    let output = ufofmt(&["--check", path_str(&test_ufo_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("would change: {}", glif_path.display())));
+    // the UFO source is not modified
+    assert_eq!(fs::read_to_string(&glif_path).unwrap(), glif_str);
+
+    let formatted_ufo_path =
+        get_test_ufo(&tmp_dir, "testdata/expected/MutatorSansBoldCondensed-default.ufo");
+    let output = ufofmt(&["--check", path_str(&formatted_ufo_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+}
+
+#[test]
+fn test_exit_code_dry_run() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path =
+        get_test_ufo(&tmp_dir, "testdata/expected/MutatorSansBoldCondensed-default.ufo");
+    let args = ["--clean", "--dry-run", path_str(&test_ufo_path)];
+    assert_eq!(ufofmt(&args).status.code(), Some(EXIT_SUCCESS));
+    let stray_path = test_ufo_path.join(".DS_Store");
+    fs::write(&stray_path, "").unwrap();
+    assert_eq!(ufofmt(&args).status.code(), Some(EXIT_CHANGES));
+    assert!(stray_path.exists());
+}
+
+#[test]
+fn test_exit_code_read_errors() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let bogus_path = tmp_dir.path().join("bogus.ufo");
+    assert_eq!(ufofmt(&[path_str(&bogus_path)]).status.code(), Some(EXIT_ERROR));
+    assert_eq!(ufofmt(&["--check", path_str(&bogus_path)]).status.code(), Some(EXIT_ERROR));
+
+    // errors take precedence over changes
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let output = ufofmt(&[path_str(&test_ufo_path), path_str(&bogus_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+
+    fs::write(test_ufo_path.join("glyphs").join("B_.glif"), "<glyph><outline>").unwrap();
+    assert_eq!(ufofmt(&[path_str(&test_ufo_path)]).status.code(), Some(EXIT_ERROR));
+}
+
+#[test]
+fn test_exit_code_stdin() {
+    let expected_glif =
+        fs::read("testdata/expected/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif").unwrap();
+    let glif = fs::read("testdata/ufo/MutatorSansBoldCondensed.ufo/glyphs/A_.glif").unwrap();
+
+    let output = ufofmt_stdin(&["--stdin-filepath", "glyphs/A_.glif"], &glif);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(output.stdout, expected_glif);
+
+    let args = ["--check", "--stdin-filepath", "glyphs/A_.glif"];
+    assert_eq!(ufofmt_stdin(&args, &glif).status.code(), Some(EXIT_CHANGES));
+    assert_eq!(ufofmt_stdin(&args, &expected_glif).status.code(), Some(EXIT_SUCCESS));
+
+    let output = ufofmt_stdin(&["--stdin-filepath", "glyphs/A_.glif"], b"<glyph><outline>");
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+}
+
+#[test]
+fn test_output_format_github() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let bogus_path = tmp_dir.path().join("bogus.ufo");
+    let output =
+        ufofmt(&["--check", "--format", "github", path_str(&test_ufo_path), path_str(&bogus_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+    assert!(stdout
+        .lines()
+        .any(|l| l.starts_with(&format!("::error file={},", glif_path.display()))));
+    assert!(stdout
+        .lines()
+        .any(|l| l.starts_with(&format!("::error file={},", bogus_path.display()))));
+    assert!(stdout.lines().all(|l| l.starts_with("::error ")));
+}
+
+#[test]
+fn test_output_format_github_format_mode() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let output = ufofmt(&["--format", "github", path_str(&test_ufo_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let glif_path = test_ufo_path.join("glyphs").join("A_.glif");
+    // rewritten files are notices, not errors
+    assert!(stdout
+        .lines()
+        .any(|l| l.starts_with(&format!("::notice file={},", glif_path.display()))
+            && l.ends_with("::file was reformatted")));
+    assert!(!stdout.contains("::error "));
+}
+
+#[test]
+fn test_output_format_sarif() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path =
+        get_test_ufo(&tmp_dir, "testdata/expected/MutatorSansBoldCondensed-default.ufo");
+    let output = ufofmt(&["--check", "--format", "sarif", "--time", path_str(&test_ufo_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    assert!(log["runs"][0]["results"].as_array().unwrap().is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Total duration"));
+}
+
+#[test]
+fn test_junit_report() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let bogus_path = tmp_dir.path().join("bogus.ufo");
+    let junit_path = tmp_dir.path().join("junit.xml");
+
+    let args = ["--junit", path_str(&junit_path), path_str(&test_ufo_path), path_str(&bogus_path)];
+    assert_eq!(ufofmt(&args).status.code(), Some(EXIT_ERROR));
+    let junit = fs::read_to_string(&junit_path).unwrap();
+    assert!(junit.contains("<testsuites name=\"ufofmt\" tests=\"2\" failures=\"1\""));
+    assert!(junit.contains(&format!(
+        "<failure type=\"invalid-file\" message=\"invalid path error: {} was not found\"",
+        bogus_path.display()
+    )));
+
+    // check mode test cases are source files
+    let args = ["--check", "--junit", path_str(&junit_path), path_str(&test_ufo_path)];
+    assert_eq!(ufofmt(&args).status.code(), Some(EXIT_SUCCESS));
+    let junit = fs::read_to_string(&junit_path).unwrap();
+    assert!(junit.contains("failures=\"0\""));
+    assert!(junit.contains("name=\"glyphs/A_.glif\"/>"));
+}
+
+#[test]
+fn test_files_from() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    let spaced_dir = tmp_dir.path().join("My Fonts");
+    fs::create_dir(&spaced_dir).unwrap();
+    let copy_res = copy(&test_ufo_path, &spaced_dir, &CopyOptions::new());
+    assert!(copy_res.is_ok());
+    let spaced_ufo_path = spaced_dir.join("MutatorSansBoldCondensed.ufo");
+
+    // newline separated lists from files
+    let list_path = tmp_dir.path().join("list.txt");
+    fs::write(
+        &list_path,
+        format!("{}\n\n{}\n", test_ufo_path.display(), spaced_ufo_path.display()),
+    )
+    .unwrap();
+    let output = ufofmt(&["--check", "--files-from", path_str(&list_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("{}\n", test_ufo_path.display())));
+    assert!(stdout.contains(&format!("{}\n", spaced_ufo_path.display())));
+
+    // NUL separated lists from stdin are formatted with the command line paths
+    let list = format!("{}\0", spaced_ufo_path.display());
+    let args = ["--files-from", "-", "-0", path_str(&test_ufo_path)];
+    let output = ufofmt_stdin(&args, list.as_bytes());
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let output = ufofmt(&["--check", "--files-from", path_str(&list_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+}
+
+#[test]
+fn test_files_from_errors() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let list_path = tmp_dir.path().join("list.txt");
+    let output = ufofmt(&["--files-from", path_str(&list_path)]);
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+    assert_eq!(ufofmt(&["-0", "test.ufo"]).status.code(), Some(EXIT_USAGE));
+    let args = ["--stdin-filepath", "glyphs/A_.glif", "--files-from", "-"];
+    assert_eq!(ufofmt_stdin(&args, b"").status.code(), Some(EXIT_USAGE));
+}
+
+#[test]
+fn test_backup_same_name_sources() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let src_ufo_path = "testdata/ufo/MutatorSansBoldCondensed.ufo";
+    for dir in &["a", "b"] {
+        let dir_path = tmp_dir.path().join(dir);
+        fs::create_dir_all(&dir_path).unwrap();
+        assert!(copy(src_ufo_path, &dir_path, &CopyOptions::new()).is_ok());
+    }
+    let glif_relpath = "MutatorSansBoldCondensed.ufo/glyphs/A_.glif";
+    let glif_str = fs::read_to_string(Path::new("testdata/ufo").join(glif_relpath)).unwrap();
+
+    let args = ["--backup", "a/MutatorSansBoldCondensed.ufo", "b/MutatorSansBoldCondensed.ufo"];
+    assert_eq!(ufofmt_in(tmp_dir.path(), &args).status.code(), Some(EXIT_CHANGES));
+    let backups_path = tmp_dir.path().join(".ufofmt-backups");
+    for dir in &["a", "b"] {
+        let backups: Vec<_> = fs::read_dir(backups_path.join(dir)).unwrap().collect();
+        assert_eq!(backups.len(), 1);
+        assert_ne!(
+            fs::read_to_string(tmp_dir.path().join(dir).join(glif_relpath)).unwrap(),
+            glif_str
+        );
+    }
+
+    // both UFO sources are restored from their own backups
+    assert_eq!(ufofmt_in(tmp_dir.path(), &["restore"]).status.code(), Some(EXIT_SUCCESS));
+    for dir in &["a", "b"] {
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join(dir).join(glif_relpath)).unwrap(),
+            glif_str
+        );
+    }
+}
+
+#[test]
+fn test_subcommand_named_ufo_sources() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let src_ufo_path = "testdata/ufo/MutatorSansBoldCondensed.ufo";
+    for name in &["restore", "lsp.ufo"] {
+        let test_ufo_path = get_test_ufo(&tmp_dir, src_ufo_path);
+        fs::rename(&test_ufo_path, tmp_dir.path().join(name)).unwrap();
+    }
+
+    // subcommand names are UFO source paths after --
+    let output = ufofmt_in(tmp_dir.path(), &["--check", "--", "restore"]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("[DRY RUN] restore\n"));
+    // paths that are similar to subcommand names are not misspelled subcommands
+    for path in &["./restore", "lsp.ufo"] {
+        let output = ufofmt_in(tmp_dir.path(), &["--check", path]);
+        assert_eq!(output.status.code(), Some(EXIT_CHANGES), "{}", path);
+    }
+    // subcommand names without -- are subcommands
+    let output = ufofmt_in(tmp_dir.path(), &["restore"]);
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+    assert!(String::from_utf8(output.stderr).unwrap().contains(".ufofmt-backup.json"));
+}
+
+#[test]
+fn test_backups_not_discovered() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let masters_path = tmp_dir.path().join("masters");
+    fs::create_dir_all(&masters_path).unwrap();
+    let src_ufo_path = "testdata/ufo/MutatorSansBoldCondensed.ufo";
+    assert!(copy(src_ufo_path, &masters_path, &CopyOptions::new()).is_ok());
+
+    let args = ["--backup", "masters/MutatorSansBoldCondensed.ufo"];
+    assert_eq!(ufofmt_in(tmp_dir.path(), &args).status.code(), Some(EXIT_CHANGES));
+    let args = ["--backup", "--backup-dir", "masters/backups", "."];
+    assert_eq!(ufofmt_in(tmp_dir.path(), &args).status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(fs::read_dir(tmp_dir.path().join(".ufofmt-backups")).unwrap().count(), 2);
+    assert_eq!(fs::read_dir(masters_path.join("backups")).unwrap().count(), 2);
+
+    // the default and user-defined backup directories are not searched
+    let output = ufofmt_in(tmp_dir.path(), &["--list-files", "."]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "./masters/MutatorSansBoldCondensed.ufo\n"
+    );
+}
+
+#[test]
+fn test_include_exclude_patterns() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let src_ufo_path = "testdata/ufo/MutatorSansBoldCondensed.ufo";
+    for dir in &["masters", "masters/archive", "vendor"] {
+        let dir_path = tmp_dir.path().join(dir);
+        fs::create_dir_all(&dir_path).unwrap();
+        assert!(copy(src_ufo_path, &dir_path, &CopyOptions::new()).is_ok());
+    }
+
+    let output = ufofmt_in(tmp_dir.path(), &["--list-files", "."]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "./masters/MutatorSansBoldCondensed.ufo\n\
+         ./masters/archive/MutatorSansBoldCondensed.ufo\n\
+         ./vendor/MutatorSansBoldCondensed.ufo\n"
+    );
+
+    let args = ["--list-files", "--include", "masters/**", "--exclude", "**/archive/**", "."];
+    let output = ufofmt_in(tmp_dir.path(), &args);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "./masters/MutatorSansBoldCondensed.ufo\n"
+    );
+
+    // configuration file patterns are combined with the command line patterns
+    fs::write(tmp_dir.path().join("ufofmt.toml"), "[files]\nexclude = [\"vendor/**\"]\n").unwrap();
+    let output = ufofmt_in(tmp_dir.path(), &["--exclude", "**/archive/**", "."]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let output = ufofmt_in(tmp_dir.path(), &["--check", "."]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("archive/MutatorSansBoldCondensed.ufo/glyphs/A_.glif"));
+    assert!(!stdout.contains("vendor"));
+    assert!(!stdout.contains("masters/MutatorSansBoldCondensed.ufo/glyphs/A_.glif"));
+
+    let output = ufofmt_in(tmp_dir.path(), &["--list-files", "--exclude", "a**", "."]);
+    assert_eq!(output.status.code(), Some(EXIT_USAGE));
+}
+
+#[test]
+fn test_since() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let repo_path = tmp_dir.path().join("repo");
+    for dir in &["masters/a", "masters/b"] {
+        let dir_path = repo_path.join(dir);
+        fs::create_dir_all(&dir_path).unwrap();
+        assert!(copy(
+            "testdata/expected/MutatorSansBoldCondensed-default.ufo",
+            &dir_path,
+            &CopyOptions::new()
+        )
+        .is_ok());
+    }
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    let output = ufofmt_in(&repo_path, &["--since", "HEAD", "--list-files"]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    assert!(output.stdout.is_empty());
+
+    let glif_path = repo_path.join("masters/b/MutatorSansBoldCondensed-default.ufo/glyphs/A_.glif");
+    let glif_str = fs::read_to_string(&glif_path).unwrap();
+    fs::write(&glif_path, glif_str.replace("\n\t", "\n  ")).unwrap();
+    let output = ufofmt_in(&repo_path, &["--since", "HEAD", "--list-files"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "./masters/b/MutatorSansBoldCondensed-default.ufo\n"
+    );
+    let output = ufofmt_in(&repo_path, &["--since", "HEAD", "masters/a"]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    let output = ufofmt_in(&repo_path, &["--since", "HEAD"]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    assert_eq!(fs::read_to_string(&glif_path).unwrap(), glif_str);
+
+    let output = ufofmt_in(&repo_path, &["--since", "bogus-revision"]);
+    assert_eq!(output.status.code(), Some(EXIT_ERROR));
+}
+
+#[test]
+fn test_line_ending() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let formatted_ufo_path =
+        get_test_ufo(&tmp_dir, "testdata/expected/MutatorSansBoldCondensed-default.ufo");
+    let ufo_arg = path_str(&formatted_ufo_path);
+    let glif_path = formatted_ufo_path.join("glyphs").join("A_.glif");
+
+    let output = ufofmt(&["--check", "--line-ending", "crlf", ufo_arg]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    assert_eq!(ufofmt(&["--line-ending", "crlf", ufo_arg]).status.code(), Some(EXIT_CHANGES));
+    assert!(fs::read_to_string(&glif_path).unwrap().contains("\r\n"));
+    // preserve keeps the CRLF line endings
+    let output = ufofmt(&["--check", "--line-ending", "preserve", ufo_arg]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    let output = ufofmt(&["--check", ufo_arg]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+
+    let output = ufofmt_stdin(
+        &["--stdin-filepath", "features.fea", "--line-ending", "preserve"],
+        b"a;\r\nb;\r\n",
+    );
+    assert_eq!(output.stdout, b"a;\r\nb;\r\n");
+    assert_eq!(ufofmt(&["--line-ending", "cr", ufo_arg]).status.code(), Some(EXIT_USAGE));
+}
+
+#[test]
+fn test_fea_include_out_dir() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let test_ufo_path = get_test_ufo(&tmp_dir, "testdata/ufo/MutatorSansBoldCondensed.ufo");
+    fs::write(test_ufo_path.join("features.fea"), "include(shared.fea);").unwrap();
+    let include_path = tmp_dir.path().join("shared.fea");
+    fs::write(&include_path, "@a=[a  b];").unwrap();
+
+    let args = ["--fea", "--out-dir", "out", "MutatorSansBoldCondensed.ufo"];
+    assert_eq!(ufofmt_in(tmp_dir.path(), &args).status.code(), Some(EXIT_CHANGES));
+    assert!(tmp_dir.path().join("out/MutatorSansBoldCondensed.ufo/features.fea").is_file());
+    // the include file in the source tree is not modified
+    assert_eq!(fs::read_to_string(&include_path).unwrap(), "@a=[a  b];");
+}
+
+#[test]
+fn test_designspace_indentation() {
+    let tmp_dir = TempDir::new("test").unwrap();
+    let config_path = tmp_dir.path().join("ufofmt.toml");
+    fs::write(&config_path, "[designspace]\nindent = \"  \"\n").unwrap();
+    let ds_path = tmp_dir.path().join("Font.designspace");
+    fs::write(&ds_path, "<designspace format=\"4.1\">\n<axes/>\n</designspace>\n").unwrap();
+
+    let output = ufofmt_in(tmp_dir.path(), &["--check", "Font.designspace"]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    let output = ufofmt_in(tmp_dir.path(), &["--backup", "Font.designspace"]);
+    assert_eq!(output.status.code(), Some(EXIT_CHANGES));
+    assert_eq!(
+        fs::read_to_string(&ds_path).unwrap(),
+        "<designspace format=\"4.1\">\n  <axes/>\n</designspace>\n"
+    );
+    let output = ufofmt_in(tmp_dir.path(), &["--check", "Font.designspace"]);
+    assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(ufofmt_in(tmp_dir.path(), &["restore"]).status.code(), Some(EXIT_SUCCESS));
+    assert_eq!(
+        fs::read_to_string(&ds_path).unwrap(),
+        "<designspace format=\"4.1\">\n<axes/>\n</designspace>\n"
+    );
+
+    let output = ufofmt_stdin(
+        &["--stdin-filepath", "Font.designspace", "--config", path_str(&config_path)],
+        b"<designspace><axes/></designspace>",
+    );
+    assert_eq!(output.stdout, b"<designspace>\n  <axes/>\n</designspace>\n");
+}